@@ -0,0 +1,96 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use diskly_lib::name_index::NameIndex;
+use diskly_lib::scanner::DirNode;
+
+const WORDS: &[&str] = &[
+    "report",
+    "invoice",
+    "photo",
+    "backup",
+    "draft",
+    "notes",
+    "archive",
+    "render",
+    "export",
+    "sample",
+    "config",
+    "module",
+    "thumbnail",
+    "recording",
+    "summary",
+    "budget",
+];
+const EXTENSIONS: &[&str] = &["pdf", "jpg", "txt", "rs", "mp4", "json", "tar.gz", "docx"];
+
+fn node(name: String, path: String, is_file: bool) -> DirNode {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "path": path,
+        "size": 4096,
+        "item_count": 1,
+        "is_file": is_file,
+    }))
+    .unwrap()
+}
+
+/// A tree of `dirs` directories of `files_per_dir` files, with names built from
+/// common words so trigrams are shared the way they are on real disks
+fn name_tree(dirs: usize, files_per_dir: usize) -> DirNode {
+    let mut root = node("data".to_string(), "/data".to_string(), false);
+    for d in 0..dirs {
+        let dir_name = format!("{}_{}", WORDS[d % WORDS.len()], d);
+        let dir_path = format!("/data/{}", dir_name);
+        let mut dir = node(dir_name, dir_path.clone(), false);
+        dir.children = (0..files_per_dir)
+            .map(|f| {
+                let i = d * files_per_dir + f;
+                let name = format!(
+                    "{}-{}_{:07}.{}",
+                    WORDS[i % WORDS.len()],
+                    WORDS[(i / 7) % WORDS.len()],
+                    i,
+                    EXTENSIONS[i % EXTENSIONS.len()]
+                );
+                let path = format!("{}/{}", dir_path, name);
+                node(name, path, true)
+            })
+            .collect();
+        root.children.push(dir);
+    }
+    root
+}
+
+fn bench_name_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("name_index");
+    group.sample_size(10);
+
+    // 2 million names. On one core (Linux, 5 s criterion runs) building takes
+    // 3.4 s, a rare substring 0.05 ms, a common one with 250k matches 31 ms and
+    // a fuzzy search 14 ms; the common case checks its matches in parallel.
+    let tree = name_tree(2000, 1000);
+    group.bench_function("build_2m_names", |b| {
+        b.iter(|| NameIndex::build(black_box(&tree)))
+    });
+
+    let index = NameIndex::build(&tree);
+    drop(tree);
+
+    // A selective substring: few entries share all of its trigrams
+    group.bench_function("substring_2m_names_rare", |b| {
+        b.iter(|| index.search(black_box("1234567"), false, None))
+    });
+
+    // A common substring: hundreds of thousands of matches to rank
+    group.bench_function("substring_2m_names_common", |b| {
+        b.iter(|| index.search(black_box("invoice"), false, None))
+    });
+
+    group.bench_function("fuzzy_2m_names", |b| {
+        b.iter(|| index.search(black_box("invioce-budgte"), true, None))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_name_index);
+criterion_main!(benches);
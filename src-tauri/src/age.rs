@@ -0,0 +1,92 @@
+use crate::scanner::DirNode;
+use serde::Serialize;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Upper bounds (in days) of the histogram buckets; files older than the last
+/// bound fall into a final open-ended bucket
+const BUCKET_BOUNDS: [(u64, &str); 7] = [
+    (7, "< 1 week"),
+    (30, "< 1 month"),
+    (90, "< 3 months"),
+    (180, "< 6 months"),
+    (365, "< 1 year"),
+    (730, "< 2 years"),
+    (1825, "< 5 years"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgeBucket {
+    pub label: String,
+    /// Exclusive upper bound in days, absent for the oldest bucket
+    pub max_age_days: Option<u64>,
+    pub size: u64,
+    pub count: u64,
+}
+
+/// Distribution of file sizes by time since last modification
+#[derive(Debug, Clone, Serialize)]
+pub struct AgeHistogram {
+    pub path: String,
+    pub buckets: Vec<AgeBucket>,
+    /// Files without a modification time
+    pub unknown_size: u64,
+    pub unknown_count: u64,
+    pub newest_mtime: Option<u64>,
+}
+
+pub fn age_histogram(node: &DirNode, now: u64) -> AgeHistogram {
+    let mut buckets: Vec<AgeBucket> = BUCKET_BOUNDS
+        .iter()
+        .map(|&(days, label)| AgeBucket {
+            label: label.to_string(),
+            max_age_days: Some(days),
+            size: 0,
+            count: 0,
+        })
+        .chain(std::iter::once(AgeBucket {
+            label: "5+ years".to_string(),
+            max_age_days: None,
+            size: 0,
+            count: 0,
+        }))
+        .collect();
+
+    let mut histogram = AgeHistogram {
+        path: node.path.clone(),
+        buckets: Vec::new(),
+        unknown_size: 0,
+        unknown_count: 0,
+        newest_mtime: node.mtime,
+    };
+    add_files(node, now, &mut buckets, &mut histogram);
+    histogram.buckets = buckets;
+    histogram
+}
+
+fn add_files(node: &DirNode, now: u64, buckets: &mut [AgeBucket], histogram: &mut AgeHistogram) {
+    if node.is_summary() {
+        return;
+    }
+    if !node.is_file {
+        for child in &node.children {
+            add_files(child, now, buckets, histogram);
+        }
+        return;
+    }
+
+    let Some(mtime) = node.mtime else {
+        histogram.unknown_size += node.size;
+        histogram.unknown_count += 1;
+        return;
+    };
+
+    // Timestamps in the future (clock skew) count as brand new
+    let age_days = now.saturating_sub(mtime) / SECS_PER_DAY;
+    let bucket = buckets
+        .iter_mut()
+        .find(|b| b.max_age_days.is_none_or(|max| age_days < max))
+        .expect("last bucket is unbounded");
+    bucket.size += node.size;
+    bucket.count += 1;
+}
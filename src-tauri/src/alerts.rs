@@ -0,0 +1,267 @@
+use crate::volumes::VolumeInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Free space must recover this far past the limit before an alert resolves,
+/// so a volume hovering around its threshold doesn't flap between states
+const RESOLVE_MARGIN: f64 = 1.05;
+
+/// A low-space limit for one mount point; the alert fires when either bound is crossed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertThreshold {
+    pub mount_point: String,
+    /// Alert when free space drops below this percentage of total space
+    #[serde(default)]
+    pub min_free_percent: Option<f64>,
+    /// Alert when free space drops below this many bytes
+    #[serde(default)]
+    pub min_free_bytes: Option<u64>,
+}
+
+impl AlertThreshold {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_free_percent.is_none() && self.min_free_bytes.is_none() {
+            return Err(format!("No limit set for {}", self.mount_point));
+        }
+        if let Some(percent) = self.min_free_percent {
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(format!("Invalid percentage for {}", self.mount_point));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the volume against the limits scaled by `margin`
+    fn is_breached(&self, volume: &VolumeInfo, margin: f64) -> bool {
+        let free = volume.available_space as f64;
+        let below_percent = self.min_free_percent.is_some_and(|percent| {
+            volume.total_space > 0 && free < volume.total_space as f64 * percent / 100.0 * margin
+        });
+        let below_bytes = self
+            .min_free_bytes
+            .is_some_and(|bytes| free < bytes as f64 * margin);
+        below_percent || below_bytes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AlertState {
+    Triggered,
+    Resolved,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeAlert {
+    pub mount_point: String,
+    pub state: AlertState,
+    pub available_space: u64,
+    pub total_space: u64,
+    pub threshold: AlertThreshold,
+}
+
+impl VolumeAlert {
+    /// Text for the native notification shown when the alert triggers
+    pub fn notification_body(&self) -> String {
+        format!(
+            "{} free of {} on {}",
+            format_bytes(self.available_space),
+            format_bytes(self.total_space),
+            self.mount_point
+        )
+    }
+}
+
+/// Evaluates thresholds against polled volumes and reports only state changes
+#[derive(Debug, Default)]
+pub struct AlertManager {
+    thresholds: Vec<AlertThreshold>,
+    /// Mount points currently below their threshold, so each crossing is reported once
+    active: HashSet<String>,
+}
+
+impl AlertManager {
+    pub fn thresholds(&self) -> &[AlertThreshold] {
+        &self.thresholds
+    }
+
+    pub fn active(&self) -> Vec<String> {
+        self.active.iter().cloned().collect()
+    }
+
+    pub fn set_thresholds(&mut self, thresholds: Vec<AlertThreshold>) -> Result<(), String> {
+        // Alert state is kept per mount point, so each volume gets a single
+        // threshold; both limits can be set on it instead
+        let mut mount_points = HashSet::new();
+        for threshold in &thresholds {
+            threshold.validate()?;
+            if !mount_points.insert(threshold.mount_point.as_str()) {
+                return Err(format!(
+                    "More than one threshold for {}",
+                    threshold.mount_point
+                ));
+            }
+        }
+
+        // Forget crossings for volumes that no longer have a threshold
+        self.active
+            .retain(|mount| thresholds.iter().any(|t| &t.mount_point == mount));
+        self.thresholds = thresholds;
+        Ok(())
+    }
+
+    /// Returns alerts for volumes that crossed a threshold since the last evaluation
+    pub fn evaluate(&mut self, volumes: &[VolumeInfo]) -> Vec<VolumeAlert> {
+        let mut alerts = Vec::new();
+
+        for threshold in &self.thresholds {
+            let Some(volume) = volumes
+                .iter()
+                .find(|v| v.mount_point == threshold.mount_point)
+            else {
+                // Unmounted volumes keep their current state until they reappear
+                continue;
+            };
+
+            let was_active = self.active.contains(&threshold.mount_point);
+            let state = if !was_active && threshold.is_breached(volume, 1.0) {
+                self.active.insert(threshold.mount_point.clone());
+                AlertState::Triggered
+            } else if was_active && !threshold.is_breached(volume, RESOLVE_MARGIN) {
+                self.active.remove(&threshold.mount_point);
+                AlertState::Resolved
+            } else {
+                continue;
+            };
+
+            alerts.push(VolumeAlert {
+                mount_point: threshold.mount_point.clone(),
+                state,
+                available_space: volume.available_space,
+                total_space: volume.total_space,
+                threshold: threshold.clone(),
+            });
+        }
+
+        alerts
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::volumes::VolumeKind;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn volume(mount_point: &str, available_space: u64) -> VolumeInfo {
+        let total_space = 100 * GB;
+        VolumeInfo {
+            name: mount_point.to_string(),
+            mount_point: mount_point.to_string(),
+            total_space,
+            available_space,
+            file_system: "ext4".to_string(),
+            is_removable: false,
+            used_percent: (total_space - available_space) as f64 / total_space as f64 * 100.0,
+            total_inodes: None,
+            free_inodes: None,
+            reserved_space: None,
+            device: "/dev/sda1".to_string(),
+            mount_options: vec![],
+            kind: VolumeKind::Physical,
+            is_bind_mount: false,
+        }
+    }
+
+    fn threshold(mount_point: &str, min_free_bytes: u64) -> AlertThreshold {
+        AlertThreshold {
+            mount_point: mount_point.to_string(),
+            min_free_percent: None,
+            min_free_bytes: Some(min_free_bytes),
+        }
+    }
+
+    fn manager() -> AlertManager {
+        let mut manager = AlertManager::default();
+        manager
+            .set_thresholds(vec![threshold("/", 10 * GB)])
+            .unwrap();
+        manager
+    }
+
+    fn states(alerts: &[VolumeAlert]) -> Vec<AlertState> {
+        alerts.iter().map(|alert| alert.state).collect()
+    }
+
+    #[test]
+    fn crossing_triggers_once() {
+        let mut manager = manager();
+        assert!(manager.evaluate(&[volume("/", 20 * GB)]).is_empty());
+        assert_eq!(
+            states(&manager.evaluate(&[volume("/", 5 * GB)])),
+            [AlertState::Triggered]
+        );
+        // Staying below the limit doesn't report again
+        assert!(manager.evaluate(&[volume("/", 4 * GB)]).is_empty());
+        assert_eq!(manager.active(), ["/"]);
+    }
+
+    #[test]
+    fn resolves_only_past_the_margin() {
+        let mut manager = manager();
+        manager.evaluate(&[volume("/", 5 * GB)]);
+
+        // Back above the limit, but within the margin
+        assert!(manager
+            .evaluate(&[volume("/", 10 * GB + GB / 4)])
+            .is_empty());
+        assert_eq!(
+            states(&manager.evaluate(&[volume("/", 11 * GB)])),
+            [AlertState::Resolved]
+        );
+        assert!(manager.active().is_empty());
+    }
+
+    #[test]
+    fn unmounted_volume_keeps_its_state() {
+        let mut manager = manager();
+        manager.evaluate(&[volume("/", 5 * GB)]);
+
+        assert!(manager.evaluate(&[]).is_empty());
+        assert_eq!(manager.active(), ["/"]);
+        // Reappearing still below the limit isn't a new crossing
+        assert!(manager.evaluate(&[volume("/", 5 * GB)]).is_empty());
+    }
+
+    #[test]
+    fn duplicate_mount_points_are_rejected() {
+        let mut manager = manager();
+        let duplicate = vec![threshold("/", 10 * GB), threshold("/", 20 * GB)];
+        assert!(manager.set_thresholds(duplicate).is_err());
+        assert_eq!(manager.thresholds().len(), 1);
+    }
+
+    #[test]
+    fn removed_threshold_forgets_its_crossing() {
+        let mut manager = manager();
+        manager.evaluate(&[volume("/", 5 * GB)]);
+        manager
+            .set_thresholds(vec![threshold("/data", GB)])
+            .unwrap();
+        assert!(manager.active().is_empty());
+    }
+}
@@ -0,0 +1,430 @@
+use crate::scanner::{DirNode, SymlinkPolicy};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+const CHECKPOINT_VERSION: u32 = 3;
+
+/// How often buffered records are written out during a scan
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Checkpoints older than this are ignored rather than resumed
+const MAX_CHECKPOINT_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// First line of a checkpoint file
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    root: String,
+    created: u64,
+    settings: CheckpointSettings,
+}
+
+/// Scan settings that shape the recorded nodes; a checkpoint written with other
+/// settings has the wrong sizes, link targets or stale totals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointSettings {
+    pub symlinks: SymlinkPolicy,
+    pub stale_days: u64,
+}
+
+/// A recorded file with more than one link. A restore enters it into the
+/// hard-link tracker, so it's counted once however its links are reached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedFile {
+    pub name: String,
+    pub volume: u64,
+    pub index: u128,
+    /// Allocated size, which the file's node lacks if another link was counted first
+    pub size: u64,
+}
+
+/// A finished directory. Subdirectories have records of their own, so they're left
+/// out of `node.children` and listed in `subdirs`, and each entry is written once.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CheckpointRecord {
+    /// Modification time of the directory itself when it was read, in nanoseconds
+    pub dir_mtime: u64,
+    pub node: DirNode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subdirs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linked: Vec<LinkedFile>,
+}
+
+/// Completed directories of an interrupted scan, keyed by path
+pub struct Checkpoint {
+    records: HashMap<String, CheckpointRecord>,
+}
+
+impl Checkpoint {
+    /// Load the checkpoint left by an unfinished scan of `root` with the same
+    /// settings, if it's recent enough
+    pub fn load(root: &Path, settings: CheckpointSettings) -> Option<Self> {
+        Self::load_from(root, settings, &checkpoint_path(root)?)
+    }
+
+    fn load_from(root: &Path, settings: CheckpointSettings, file_path: &Path) -> Option<Self> {
+        let file = File::open(file_path).ok()?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header: Header = serde_json::from_str(&lines.next()?.ok()?).ok()?;
+        let age = crate::scanner::now_secs().saturating_sub(header.created);
+        if header.version != CHECKPOINT_VERSION
+            || header.settings != settings
+            || Path::new(&header.root) != root
+            || age > MAX_CHECKPOINT_AGE_SECS
+        {
+            return None;
+        }
+
+        // A crash can leave the last line half written; keep everything before it
+        let records: HashMap<String, CheckpointRecord> = lines
+            .map_while(|line| serde_json::from_str::<CheckpointRecord>(&line.ok()?).ok())
+            .map(|record| (record.node.path.clone(), record))
+            .collect();
+        (!records.is_empty()).then_some(Self { records })
+    }
+
+    pub fn get(&self, path: &str) -> Option<&CheckpointRecord> {
+        self.records.get(path)
+    }
+}
+
+/// Appends finished directories of a running scan to its checkpoint file
+pub struct CheckpointWriter {
+    file_path: PathBuf,
+    /// Written first, and renamed over `file_path` on the first flush so the
+    /// previous checkpoint survives until the new one has something in it
+    temp_path: PathBuf,
+    out: Mutex<Output>,
+    /// Directories already written, which parent records refer to by path
+    recorded: Mutex<HashSet<String>>,
+}
+
+struct Output {
+    writer: BufWriter<File>,
+    last_flush: Instant,
+    /// Whether `temp_path` has been renamed into place
+    promoted: bool,
+}
+
+impl CheckpointWriter {
+    /// Start a fresh checkpoint for `root`, replacing any previous one once it's flushed
+    pub fn create(root: &Path, settings: CheckpointSettings) -> Result<Self, String> {
+        let file_path = checkpoint_path(root).ok_or("No cache directory available")?;
+        Self::create_at(root, settings, file_path)
+    }
+
+    fn create_at(
+        root: &Path,
+        settings: CheckpointSettings,
+        file_path: PathBuf,
+    ) -> Result<Self, String> {
+        if let Some(dir) = file_path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create checkpoint directory: {}", e))?;
+        }
+
+        let temp_path = file_path.with_extension("jsonl.tmp");
+        let file =
+            File::create(&temp_path).map_err(|e| format!("Failed to create checkpoint: {}", e))?;
+        let mut out = BufWriter::new(file);
+        let header = Header {
+            version: CHECKPOINT_VERSION,
+            root: root.to_string_lossy().to_string(),
+            created: crate::scanner::now_secs(),
+            settings,
+        };
+        serde_json::to_writer(&mut out, &header)
+            .map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+        out.write_all(b"\n")
+            .map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+
+        Ok(Self {
+            file_path,
+            temp_path,
+            out: Mutex::new(Output {
+                writer: out,
+                last_flush: Instant::now(),
+                promoted: false,
+            }),
+            recorded: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Record a finished directory and the files in it that have other links.
+    /// Write errors are ignored: a checkpoint is only a head start for the next scan.
+    pub fn record(&self, node: &DirNode, metadata: &fs::Metadata, linked: Vec<LinkedFile>) {
+        // Files are written as part of their directory's record
+        if node.is_file {
+            return;
+        }
+        let Some(dir_mtime) = mtime_nanos(metadata) else {
+            return;
+        };
+
+        // Children finish before their parent, so recorded subdirectories are known
+        // here. Only unreadable directories, which never finish, are kept inline.
+        let mut record = CheckpointRecord {
+            dir_mtime,
+            node: node.clone_shallow(),
+            subdirs: Vec::new(),
+            linked,
+        };
+        {
+            let mut recorded = self.recorded.lock().expect("Checkpoint lock poisoned");
+            for child in &node.children {
+                if !child.is_file && recorded.contains(&child.path) {
+                    record.subdirs.push(child.path.clone());
+                } else {
+                    record.node.children.push(child.clone());
+                }
+            }
+            recorded.insert(node.path.clone());
+        }
+
+        let mut out = self.out.lock().expect("Checkpoint lock poisoned");
+        if serde_json::to_writer(&mut out.writer, &record).is_ok() {
+            let _ = out.writer.write_all(b"\n");
+        }
+        if out.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush_output(&mut out);
+        }
+    }
+
+    /// Write out buffered records so an interrupted scan can resume from them
+    pub fn flush(&self) {
+        self.flush_output(&mut self.out.lock().expect("Checkpoint lock poisoned"));
+    }
+
+    fn flush_output(&self, out: &mut Output) {
+        if out.writer.flush().is_ok() && !out.promoted {
+            out.promoted = fs::rename(&self.temp_path, &self.file_path).is_ok();
+        }
+        out.last_flush = Instant::now();
+    }
+
+    /// Delete the checkpoint, and any previous one, once the scan has completed
+    pub fn discard(self) {
+        drop(self.out);
+        let _ = fs::remove_file(&self.temp_path);
+        let _ = fs::remove_file(&self.file_path);
+    }
+}
+
+/// Modification time of an entry in nanoseconds, precise enough to notice changes
+pub fn mtime_nanos(metadata: &fs::Metadata) -> Option<u64> {
+    let since_epoch = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(since_epoch.as_nanos()).ok()
+}
+
+/// File name derived from the root with a hash that stays the same across releases
+fn checkpoint_path(root: &Path) -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| {
+        dir.join("diskly").join("checkpoints").join(format!(
+            "{:016x}.jsonl",
+            fnv1a(root.as_os_str().as_encoded_bytes())
+        ))
+    })
+}
+
+/// 64-bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::ScannerCore;
+    use tempfile::TempDir;
+
+    const FILES: &[&str] = &["top.txt", "a/one.txt", "a/b/two.txt", "a/b/c/d/e/deep.txt"];
+
+    fn tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for file in FILES {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"contents").unwrap();
+        }
+        dir
+    }
+
+    fn settings() -> CheckpointSettings {
+        ScannerCore::new().checkpoint_settings()
+    }
+
+    /// Scan `root` completely, recording every directory into a checkpoint file
+    fn checkpoint(root: &Path, file_path: &Path) -> (DirNode, Checkpoint) {
+        let core = ScannerCore::new();
+        let writer =
+            CheckpointWriter::create_at(root, settings(), file_path.to_path_buf()).unwrap();
+        let node = core
+            .scan_path(root, &|node, metadata| {
+                writer.record(node, metadata, core.linked_files(node))
+            })
+            .unwrap();
+        writer.flush();
+        (
+            node,
+            Checkpoint::load_from(root, settings(), file_path).unwrap(),
+        )
+    }
+
+    fn resume(root: &Path, checkpoint: Checkpoint) -> DirNode {
+        let core = ScannerCore::new();
+        core.resume_from(Some(checkpoint));
+        core.scan_path(root, &|_, _| {}).unwrap()
+    }
+
+    /// `relative` below `root`, without the trailing separator `join("")` adds
+    fn below(root: &Path, relative: &str) -> PathBuf {
+        if relative.is_empty() {
+            root.to_path_buf()
+        } else {
+            root.join(relative)
+        }
+    }
+
+    /// Rewrite a file with more contents, which leaves its directory's mtime
+    /// alone, so a restored directory still reports the old size
+    fn grow(path: &Path) {
+        fs::write(path, vec![b'x'; 256 * 1024]).unwrap();
+    }
+
+    fn size_of(node: &DirNode, path: &Path) -> u64 {
+        node.find(path).expect("node").size
+    }
+
+    #[test]
+    fn round_trip_restores_the_scanned_tree() {
+        let dir = tree();
+        let cache = TempDir::new().unwrap();
+        let (scanned, checkpoint) = checkpoint(dir.path(), &cache.path().join("ck.jsonl"));
+
+        grow(&dir.path().join("top.txt"));
+        grow(&dir.path().join("a/b/c/d/e/deep.txt"));
+        let restored = resume(dir.path(), checkpoint);
+        assert_eq!(restored.size, scanned.size);
+        assert_eq!(restored.item_count, FILES.len() as u64);
+        let deep = dir.path().join("a/b/c/d/e/deep.txt");
+        assert!(restored.find(&deep).is_some_and(|node| node.is_file));
+    }
+
+    #[test]
+    fn every_directory_gets_its_own_record() {
+        let dir = tree();
+        let cache = TempDir::new().unwrap();
+        let (_, checkpoint) = checkpoint(dir.path(), &cache.path().join("ck.jsonl"));
+
+        for subdir in ["", "a", "a/b", "a/b/c", "a/b/c/d", "a/b/c/d/e"] {
+            let path = below(dir.path(), subdir);
+            let record = checkpoint.get(&path.to_string_lossy()).expect("record");
+            assert!(record.node.children.iter().all(|child| child.is_file));
+        }
+    }
+
+    #[test]
+    fn changed_directory_is_not_restored() {
+        let dir = tree();
+        let cache = TempDir::new().unwrap();
+        let (scanned, checkpoint) = checkpoint(dir.path(), &cache.path().join("ck.jsonl"));
+
+        let one = dir.path().join("a/one.txt");
+        let two = dir.path().join("a/b/two.txt");
+        grow(&one);
+        grow(&two);
+        fs::write(dir.path().join("a/new.txt"), b"new").unwrap();
+
+        let resumed = resume(dir.path(), checkpoint);
+        assert!(size_of(&resumed, &one) > size_of(&scanned, &one));
+        assert!(resumed.find(&dir.path().join("a/new.txt")).is_some());
+        // Subdirectories of the change are still good
+        assert_eq!(size_of(&resumed, &two), size_of(&scanned, &two));
+    }
+
+    #[test]
+    fn deep_change_is_picked_up_below_restored_ancestors() {
+        let dir = tree();
+        let cache = TempDir::new().unwrap();
+        let (scanned, checkpoint) = checkpoint(dir.path(), &cache.path().join("ck.jsonl"));
+
+        let top = dir.path().join("top.txt");
+        grow(&top);
+        fs::write(dir.path().join("a/b/c/d/e/new.txt"), b"new").unwrap();
+
+        // Ancestors are restored, but the changed directory is read again
+        let resumed = resume(dir.path(), checkpoint);
+        assert_eq!(size_of(&resumed, &top), size_of(&scanned, &top));
+        assert_eq!(resumed.item_count, FILES.len() as u64 + 1);
+    }
+
+    #[test]
+    fn checkpoint_from_other_settings_is_ignored() {
+        let dir = tree();
+        let cache = TempDir::new().unwrap();
+        let file_path = cache.path().join("ck.jsonl");
+        checkpoint(dir.path(), &file_path);
+
+        let other = CheckpointSettings {
+            stale_days: settings().stale_days + 1,
+            ..settings()
+        };
+        assert!(Checkpoint::load_from(dir.path(), other, &file_path).is_none());
+        assert!(Checkpoint::load_from(dir.path(), settings(), &file_path).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_are_counted_once_after_a_partial_restore() {
+        for changed in ["x", "y"] {
+            let dir = TempDir::new().unwrap();
+            let cache = TempDir::new().unwrap();
+            fs::create_dir_all(dir.path().join("x")).unwrap();
+            fs::create_dir_all(dir.path().join("y")).unwrap();
+            fs::write(dir.path().join("x/file"), vec![b'x'; 64 * 1024]).unwrap();
+            fs::hard_link(dir.path().join("x/file"), dir.path().join("y/link")).unwrap();
+            let (_, checkpoint) = checkpoint(dir.path(), &cache.path().join("ck.jsonl"));
+
+            // One link's directory is read again, the other is restored
+            fs::write(dir.path().join(changed).join("new.txt"), b"new").unwrap();
+            let resumed = resume(dir.path(), checkpoint);
+            let fresh = ScannerCore::new()
+                .scan_path(dir.path(), &|_, _| {})
+                .unwrap();
+            assert_eq!(resumed.size, fresh.size, "changed {}", changed);
+        }
+    }
+
+    #[test]
+    fn previous_checkpoint_survives_until_the_first_flush() {
+        let dir = tree();
+        let cache = TempDir::new().unwrap();
+        let file_path = cache.path().join("ck.jsonl");
+        checkpoint(dir.path(), &file_path);
+
+        let writer =
+            CheckpointWriter::create_at(dir.path(), settings(), file_path.clone()).unwrap();
+        assert!(Checkpoint::load_from(dir.path(), settings(), &file_path).is_some());
+        writer.flush();
+        // Nothing recorded yet, so the new checkpoint is empty
+        assert!(Checkpoint::load_from(dir.path(), settings(), &file_path).is_none());
+        writer.discard();
+        assert!(!file_path.exists());
+    }
+
+    #[test]
+    fn file_name_hash_is_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+}
@@ -0,0 +1,452 @@
+use crate::cleanup_rules::RuleError;
+use crate::scanner::{now_secs, DirNode};
+use globset::GlobMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupCategory {
+    /// Installed packages that a package manager can restore
+    Dependencies,
+    /// Compiler and bundler output
+    BuildOutput,
+    /// Tool and application caches
+    Cache,
+    /// IDE indexes and derived data
+    IdeData,
+    /// Category named by a user-defined rule
+    #[serde(untagged)]
+    Custom(String),
+}
+
+/// How a rule recognises a directory
+#[derive(Debug, Clone)]
+pub enum DirMatcher {
+    /// Any directory with this name
+    Name(String),
+    /// A path relative to the user's home directory
+    HomeRelative(String),
+    /// An absolute path
+    Absolute(String),
+    /// Glob matched against the directory name, or the full path if it contains `/`
+    Glob {
+        matcher: GlobMatcher,
+        whole_path: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct CleanupRule {
+    pub name: String,
+    pub category: CleanupCategory,
+    pub matcher: DirMatcher,
+    /// File that must exist next to the matched directory
+    pub marker: Option<String>,
+    pub min_size: Option<u64>,
+    /// Minimum time since anything in the directory was last modified
+    pub min_age: Option<Duration>,
+    pub suggested_action: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupCandidate {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub item_count: u64,
+    pub rule: String,
+    pub category: CleanupCategory,
+    pub suggested_action: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryTotal {
+    pub category: CleanupCategory,
+    pub size: u64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupReport {
+    /// Candidates sorted by size, largest first
+    pub candidates: Vec<CleanupCandidate>,
+    pub categories: Vec<CategoryTotal>,
+    pub total_reclaimable: u64,
+    /// Problems loading user-defined rules; valid rules are still applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_errors: Vec<RuleError>,
+}
+
+pub fn builtin_rules() -> Vec<CleanupRule> {
+    use CleanupCategory::*;
+    use DirMatcher::*;
+
+    let rule =
+        |name: &str, category, matcher, marker: Option<&str>, suggested_action: &str| CleanupRule {
+            name: name.to_string(),
+            category,
+            matcher,
+            marker: marker.map(str::to_string),
+            min_size: None,
+            min_age: None,
+            suggested_action: suggested_action.to_string(),
+        };
+
+    vec![
+        rule(
+            "node_modules",
+            Dependencies,
+            Name("node_modules".into()),
+            Some("package.json"),
+            "Delete; reinstall with your package manager",
+        ),
+        rule(
+            "Rust target",
+            BuildOutput,
+            Name("target".into()),
+            Some("Cargo.toml"),
+            "Run `cargo clean` or delete",
+        ),
+        rule(
+            "Gradle project cache",
+            BuildOutput,
+            Name(".gradle".into()),
+            None,
+            "Delete; Gradle recreates it on the next build",
+        ),
+        rule(
+            "Python bytecode",
+            Cache,
+            Name("__pycache__".into()),
+            None,
+            "Delete; Python regenerates it on import",
+        ),
+        rule(
+            "User cache",
+            Cache,
+            HomeRelative(".cache".into()),
+            None,
+            "Delete entries for tools you no longer use",
+        ),
+        rule(
+            "Docker buildx cache",
+            Cache,
+            HomeRelative(".docker/buildx".into()),
+            None,
+            "Run `docker buildx prune`",
+        ),
+        rule(
+            "Docker BuildKit cache",
+            Cache,
+            Absolute("/var/lib/docker/buildkit".into()),
+            None,
+            "Run `docker builder prune`",
+        ),
+        rule(
+            "JetBrains caches",
+            IdeData,
+            HomeRelative("Library/Caches/JetBrains".into()),
+            None,
+            "Delete folders of IDE versions you no longer use",
+        ),
+        rule(
+            "VS Code cached data",
+            IdeData,
+            HomeRelative(".config/Code/CachedData".into()),
+            None,
+            "Delete; VS Code rebuilds it on start",
+        ),
+        rule(
+            "VS Code cached data",
+            IdeData,
+            HomeRelative("Library/Application Support/Code/CachedData".into()),
+            None,
+            "Delete; VS Code rebuilds it on start",
+        ),
+        rule(
+            "Xcode derived data",
+            IdeData,
+            HomeRelative("Library/Developer/Xcode/DerivedData".into()),
+            None,
+            "Delete; Xcode rebuilds it",
+        ),
+    ]
+}
+
+/// Walk a scan tree, starting with the root itself, and collect directories matched
+/// by `rules`. Matched directories aren't descended into, so nested matches aren't
+/// double counted.
+pub fn find_candidates(root: &DirNode, rules: &[CleanupRule]) -> CleanupReport {
+    let home = dirs::home_dir();
+    let mut candidates = Vec::new();
+    let root_rule = (!root.is_file)
+        .then(|| {
+            rules
+                .iter()
+                .find(|rule| rule.matches(root, None, home.as_deref()))
+        })
+        .flatten();
+    match root_rule {
+        Some(rule) => candidates.push(CleanupCandidate::new(root, rule)),
+        None => collect_candidates(root, rules, home.as_deref(), &mut candidates),
+    }
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.size));
+
+    let mut totals: BTreeMap<CleanupCategory, CategoryTotal> = BTreeMap::new();
+    for candidate in &candidates {
+        let total = totals
+            .entry(candidate.category.clone())
+            .or_insert_with(|| CategoryTotal {
+                category: candidate.category.clone(),
+                size: 0,
+                count: 0,
+            });
+        total.size += candidate.size;
+        total.count += 1;
+    }
+
+    CleanupReport {
+        total_reclaimable: candidates.iter().map(|c| c.size).sum(),
+        candidates,
+        categories: totals.into_values().collect(),
+        rule_errors: Vec::new(),
+    }
+}
+
+fn collect_candidates(
+    node: &DirNode,
+    rules: &[CleanupRule],
+    home: Option<&Path>,
+    candidates: &mut Vec<CleanupCandidate>,
+) {
+    for child in node
+        .children
+        .iter()
+        .filter(|c| !c.is_file && !c.is_summary())
+    {
+        match rules
+            .iter()
+            .find(|rule| rule.matches(child, Some(node), home))
+        {
+            Some(rule) => candidates.push(CleanupCandidate::new(child, rule)),
+            None => collect_candidates(child, rules, home, candidates),
+        }
+    }
+}
+
+impl CleanupCandidate {
+    fn new(dir: &DirNode, rule: &CleanupRule) -> Self {
+        Self {
+            name: dir.name.clone(),
+            path: dir.path.clone(),
+            size: dir.size,
+            item_count: dir.item_count,
+            rule: rule.name.clone(),
+            category: rule.category.clone(),
+            suggested_action: rule.suggested_action.clone(),
+        }
+    }
+}
+
+impl CleanupRule {
+    /// `parent` is absent for the scan root, whose siblings aren't in the tree
+    fn matches(&self, dir: &DirNode, parent: Option<&DirNode>, home: Option<&Path>) -> bool {
+        let path_matches = match &self.matcher {
+            DirMatcher::Name(name) => &dir.name == name,
+            DirMatcher::HomeRelative(relative) => {
+                home.is_some_and(|home| Path::new(&dir.path) == home.join(relative))
+            }
+            DirMatcher::Absolute(path) => Path::new(&dir.path) == Path::new(path),
+            DirMatcher::Glob {
+                matcher,
+                whole_path,
+            } => {
+                if *whole_path {
+                    matcher.is_match(&dir.path)
+                } else {
+                    matcher.is_match(&dir.name)
+                }
+            }
+        };
+
+        // Markers are checked against the scan tree, and only looked up on disk
+        // next to the scan root
+        path_matches
+            && self.min_size.is_none_or(|min| dir.size >= min)
+            && self.marker.as_ref().is_none_or(|marker| match parent {
+                Some(parent) => parent
+                    .children
+                    .iter()
+                    .any(|sibling| sibling.is_file && &sibling.name == marker),
+                None => Path::new(&dir.path)
+                    .parent()
+                    .is_some_and(|parent| parent.join(marker).is_file()),
+            })
+            && self
+                .min_age
+                .is_none_or(|min_age| is_older_than(dir, min_age))
+    }
+}
+
+/// Age is taken from the newest modification time anywhere in the subtree, so
+/// that changes to nested files keep a directory young
+fn is_older_than(dir: &DirNode, min_age: Duration) -> bool {
+    dir.mtime
+        .is_some_and(|mtime| now_secs().saturating_sub(mtime) >= min_age.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn node(path: &str, is_file: bool, size: u64, children: Vec<DirNode>) -> DirNode {
+        serde_json::from_value(json!({
+            "name": Path::new(path).file_name().unwrap().to_string_lossy(),
+            "path": path,
+            "size": size,
+            "item_count": 1,
+            "is_file": is_file,
+            "children": children,
+        }))
+        .unwrap()
+    }
+
+    fn dir(path: &str, children: Vec<DirNode>) -> DirNode {
+        let size = children.iter().map(|c| c.size).sum();
+        node(path, false, size, children)
+    }
+
+    fn file(path: &str) -> DirNode {
+        node(path, true, 1, vec![])
+    }
+
+    fn candidate_paths(root: &DirNode) -> Vec<(String, String)> {
+        let mut paths: Vec<_> = find_candidates(root, &builtin_rules())
+            .candidates
+            .into_iter()
+            .map(|c| (c.path, c.rule))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn marked_directories_are_candidates() {
+        let root = dir(
+            "/p",
+            vec![
+                dir(
+                    "/p/app",
+                    vec![
+                        file("/p/app/package.json"),
+                        dir(
+                            "/p/app/node_modules",
+                            vec![file("/p/app/node_modules/x.js")],
+                        ),
+                    ],
+                ),
+                dir(
+                    "/p/crate",
+                    vec![
+                        file("/p/crate/Cargo.toml"),
+                        dir("/p/crate/target", vec![file("/p/crate/target/bin")]),
+                    ],
+                ),
+            ],
+        );
+
+        assert_eq!(
+            candidate_paths(&root),
+            vec![
+                (
+                    "/p/app/node_modules".to_string(),
+                    "node_modules".to_string()
+                ),
+                ("/p/crate/target".to_string(), "Rust target".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmarked_directories_are_not_candidates() {
+        let root = dir(
+            "/p",
+            vec![
+                // A `target` that isn't Cargo's, and node_modules without package.json
+                dir(
+                    "/p/site",
+                    vec![
+                        file("/p/site/Makefile"),
+                        dir("/p/site/target", vec![file("/p/site/target/page.html")]),
+                        dir(
+                            "/p/site/node_modules",
+                            vec![file("/p/site/node_modules/x.js")],
+                        ),
+                    ],
+                ),
+                // A directory named like the marker doesn't count
+                dir(
+                    "/p/odd",
+                    vec![
+                        dir("/p/odd/Cargo.toml", vec![]),
+                        dir("/p/odd/target", vec![file("/p/odd/target/bin")]),
+                    ],
+                ),
+            ],
+        );
+
+        assert!(candidate_paths(&root).is_empty());
+    }
+
+    #[test]
+    fn matched_directories_are_not_descended_into() {
+        let root = dir(
+            "/p",
+            vec![
+                file("/p/package.json"),
+                dir(
+                    "/p/node_modules",
+                    vec![dir(
+                        "/p/node_modules/dep",
+                        vec![
+                            file("/p/node_modules/dep/package.json"),
+                            dir(
+                                "/p/node_modules/dep/node_modules",
+                                vec![file("/p/node_modules/dep/node_modules/x")],
+                            ),
+                        ],
+                    )],
+                ),
+            ],
+        );
+
+        assert_eq!(
+            candidate_paths(&root),
+            vec![("/p/node_modules".to_string(), "node_modules".to_string())]
+        );
+    }
+
+    #[test]
+    fn the_scan_root_itself_can_be_a_candidate() {
+        let project = TempDir::new().unwrap();
+        let modules = project.path().join("node_modules");
+        let modules = modules.to_string_lossy();
+        let root = dir(&modules, vec![file(&format!("{}/x.js", modules))]);
+
+        // The marker next to the root is only on disk
+        assert!(candidate_paths(&root).is_empty());
+        std::fs::write(project.path().join("package.json"), b"{}").unwrap();
+        assert_eq!(
+            candidate_paths(&root),
+            vec![(modules.to_string(), "node_modules".to_string())]
+        );
+
+        let report = find_candidates(&root, &builtin_rules());
+        assert_eq!(report.total_reclaimable, 1);
+    }
+}
@@ -0,0 +1,409 @@
+use crate::cleanup::{find_candidates, CleanupCandidate, CleanupCategory, CleanupRule, DirMatcher};
+use crate::scanner::DirNode;
+use crate::util::parse_size;
+use globset::GlobBuilder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::de::{DeTable, ValueDeserializer};
+use toml::Spanned;
+
+const RULES_FILE_NAME: &str = "cleanup-rules.toml";
+const DEFAULT_ACTION: &str = "Review and delete";
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// `<config dir>/diskly/cleanup-rules.toml`, e.g. ~/.config/diskly on Linux
+pub fn default_rules_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("diskly").join(RULES_FILE_NAME))
+}
+
+/// A problem with the rules file, or with a single `[[rule]]` entry
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleError {
+    /// Index of the `[[rule]]` entry, absent for file-level errors
+    pub rule_index: Option<usize>,
+    pub rule_name: Option<String>,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadedRules {
+    pub rules: Vec<CleanupRule>,
+    pub errors: Vec<RuleError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleValidation {
+    pub path: String,
+    pub exists: bool,
+    pub rule_count: usize,
+    pub errors: Vec<RuleError>,
+}
+
+/// Matches of every rule, each evaluated on its own
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTestReport {
+    pub rules: Vec<RuleMatches>,
+}
+
+/// Paths matched by a single rule when evaluated on its own
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleMatches {
+    pub rule: String,
+    pub is_builtin: bool,
+    pub matches: Vec<CleanupCandidate>,
+    pub total_size: u64,
+}
+
+/// One `[[rule]]` entry:
+///
+/// ```toml
+/// [[rule]]
+/// name = "Bazel output"
+/// glob = "bazel-out"        # matches names; patterns with `/` match full paths
+/// marker = "WORKSPACE"      # optional sibling file
+/// min_age_days = 30         # optional
+/// min_size = "1 GB"         # optional, bytes or a size with unit
+/// category = "build_output" # built-in category or any custom name
+/// action = "Run `bazel clean`"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    name: String,
+    glob: Spanned<String>,
+    marker: Option<String>,
+    min_age_days: Option<u64>,
+    min_size: Option<Spanned<SizeValue>>,
+    category: Option<CleanupCategory>,
+    action: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeValue {
+    Bytes(u64),
+    Text(String),
+}
+
+/// Load rules from `path`. A missing file yields no rules and no errors; invalid
+/// entries are reported and skipped so the remaining rules still apply.
+pub fn load_rules(path: &Path) -> LoadedRules {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return LoadedRules::default(),
+        Err(e) => return file_error(format!("Cannot read {}: {}", path.display(), e), None),
+    };
+
+    // Rules are deserialized one by one from the spanned document, so that a bad
+    // entry doesn't hide the others and errors point at the offending value
+    let document = match DeTable::parse(&content) {
+        Ok(document) => document,
+        Err(e) => {
+            let line = e.span().map(|span| line_of(&content, span.start));
+            return file_error(e.message().to_string(), line);
+        }
+    };
+    let Some(entries) = document.get_ref().get("rule") else {
+        return LoadedRules::default();
+    };
+    let Some(entries) = entries.get_ref().as_array() else {
+        let line = line_of(&content, entries.span().start);
+        return file_error(
+            "Rules must be written as [[rule]] tables".to_string(),
+            Some(line),
+        );
+    };
+
+    let mut loaded = LoadedRules::default();
+    for (index, entry) in entries.iter().enumerate() {
+        let rule_name = entry
+            .get_ref()
+            .get("name")
+            .and_then(|name| name.get_ref().as_str())
+            .map(str::to_string);
+
+        let parsed = RuleDefinition::deserialize(ValueDeserializer::from(entry.clone()))
+            .map_err(|e| {
+                let offset = e.span().map_or(entry.span().start, |span| span.start);
+                (e.message().to_string(), offset)
+            })
+            .and_then(build_rule);
+
+        match parsed {
+            Ok(rule) => loaded.rules.push(rule),
+            Err((message, offset)) => loaded.errors.push(RuleError {
+                rule_index: Some(index),
+                rule_name,
+                line: Some(line_of(&content, offset)),
+                message,
+            }),
+        }
+    }
+    loaded
+}
+
+pub fn validate_rules(path: &Path) -> RuleValidation {
+    let loaded = load_rules(path);
+    RuleValidation {
+        path: path.to_string_lossy().to_string(),
+        exists: path.exists(),
+        rule_count: loaded.rules.len(),
+        errors: loaded.errors,
+    }
+}
+
+/// Evaluate each rule independently against a scan tree
+pub fn test_rules(root: &DirNode, user_rules: &[CleanupRule]) -> RuleTestReport {
+    let builtin = crate::cleanup::builtin_rules();
+    let rules = user_rules
+        .iter()
+        .map(|rule| (rule, false))
+        .chain(builtin.iter().map(|rule| (rule, true)))
+        .map(|(rule, is_builtin)| {
+            let report = find_candidates(root, std::slice::from_ref(rule));
+            RuleMatches {
+                rule: rule.name.clone(),
+                is_builtin,
+                total_size: report.total_reclaimable,
+                matches: report.candidates,
+            }
+        })
+        .collect();
+    RuleTestReport { rules }
+}
+
+/// Build a rule, or fail with a message and the offset of the value at fault
+fn build_rule(definition: RuleDefinition) -> Result<CleanupRule, (String, usize)> {
+    let glob = definition.glob.get_ref();
+    let pattern = match (glob.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(relative), Some(home)) => home.join(relative).to_string_lossy().to_string(),
+        _ => glob.clone(),
+    };
+    let matcher = GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| (format!("Invalid glob: {}", e), definition.glob.span().start))?
+        .compile_matcher();
+
+    let min_size = match definition.min_size {
+        None => None,
+        Some(size) => match size.get_ref() {
+            SizeValue::Bytes(bytes) => Some(*bytes),
+            SizeValue::Text(text) => {
+                Some(parse_size(text).map_err(|message| (message, size.span().start))?)
+            }
+        },
+    };
+
+    Ok(CleanupRule {
+        name: definition.name,
+        category: definition
+            .category
+            .unwrap_or_else(|| CleanupCategory::Custom("custom".to_string())),
+        matcher: DirMatcher::Glob {
+            matcher,
+            whole_path: pattern.contains('/'),
+        },
+        marker: definition.marker,
+        min_size,
+        min_age: definition
+            .min_age_days
+            .map(|days| Duration::from_secs(days.saturating_mul(SECS_PER_DAY))),
+        suggested_action: definition
+            .action
+            .unwrap_or_else(|| DEFAULT_ACTION.to_string()),
+    })
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+fn file_error(message: String, line: Option<usize>) -> LoadedRules {
+    LoadedRules {
+        rules: Vec::new(),
+        errors: vec![RuleError {
+            rule_index: None,
+            rule_name: None,
+            line,
+            message,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::now_secs;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn write_rules(content: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(RULES_FILE_NAME);
+        fs::write(&path, content).unwrap();
+        (dir, path)
+    }
+
+    fn dir(path: &str, size: u64, mtime: u64, children: Vec<DirNode>) -> DirNode {
+        serde_json::from_value(json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "size": size,
+            "item_count": children.len(),
+            "is_file": false,
+            "mtime": mtime,
+            "children": children,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn invalid_rules_are_reported_at_the_offending_line() {
+        let (_dir, path) = write_rules(
+            r#"# Project rules
+
+[[rule]]
+name = "Bazel output"
+glob = "bazel-out"
+
+[[rule]]
+name = "Bad size"
+glob = "dist"
+min_size = "lots"
+
+[[rule]]
+name = "Bad glob"
+glob = "build["
+
+[[rule]]
+name = "Typo"
+glob = "out"
+min_age = 30
+"#,
+        );
+
+        let validation = validate_rules(&path);
+        assert!(validation.exists);
+        assert_eq!(validation.rule_count, 1);
+        let errors: Vec<_> = validation
+            .errors
+            .iter()
+            .map(|e| (e.rule_index, e.rule_name.as_deref(), e.line))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (Some(1), Some("Bad size"), Some(10)),
+                (Some(2), Some("Bad glob"), Some(14)),
+                (Some(3), Some("Typo"), Some(19)),
+            ]
+        );
+        assert!(validation.errors[2].message.contains("min_age"));
+    }
+
+    #[test]
+    fn rules_with_the_same_name_get_their_own_line() {
+        let (_dir, path) = write_rules(
+            r#"[[rule]]
+name = "Output"
+glob = "out"
+
+[[rule]]
+name = "Output"
+glob = "out"
+marker = 1
+"#,
+        );
+
+        let loaded = load_rules(&path);
+        assert_eq!(loaded.rules.len(), 1);
+        assert_eq!(loaded.errors.len(), 1);
+        assert_eq!(loaded.errors[0].line, Some(8));
+    }
+
+    #[test]
+    fn syntax_errors_are_reported_for_the_whole_file() {
+        let (_dir, path) = write_rules("[[rule]]\nname = \"Broken\nglob = \"x\"\n");
+
+        let loaded = load_rules(&path);
+        assert!(loaded.rules.is_empty());
+        assert_eq!(loaded.errors.len(), 1);
+        assert_eq!(loaded.errors[0].rule_index, None);
+        assert_eq!(loaded.errors[0].line, Some(2));
+    }
+
+    #[test]
+    fn a_missing_file_has_no_rules_and_no_errors() {
+        let dir = TempDir::new().unwrap();
+        let validation = validate_rules(&dir.path().join(RULES_FILE_NAME));
+        assert!(!validation.exists);
+        assert_eq!(validation.rule_count, 0);
+        assert!(validation.errors.is_empty());
+    }
+
+    #[test]
+    fn huge_minimum_ages_saturate() {
+        let (_dir, path) = write_rules(
+            "[[rule]]\nname = \"Ancient\"\nglob = \"old\"\nmin_age_days = 9223372036854775807\n",
+        );
+
+        let loaded = load_rules(&path);
+        assert!(loaded.errors.is_empty());
+        assert_eq!(loaded.rules[0].min_age, Some(Duration::from_secs(u64::MAX)));
+    }
+
+    #[test]
+    fn test_mode_evaluates_each_rule_on_its_own() {
+        let (_dir, path) = write_rules(
+            r#"[[rule]]
+name = "Old output"
+glob = "out"
+min_age_days = 30
+
+[[rule]]
+name = "Any output"
+glob = "out"
+"#,
+        );
+        let rules = load_rules(&path).rules;
+
+        let now = now_secs();
+        // Something inside b/out changed yesterday, which its subtree mtime carries
+        let touched = dir(
+            "/p/b/out",
+            20,
+            now - DAY,
+            vec![dir("/p/b/out/cache", 20, now - DAY, vec![])],
+        );
+        let root = dir(
+            "/p",
+            30,
+            now - DAY,
+            vec![
+                dir(
+                    "/p/a",
+                    10,
+                    now - 90 * DAY,
+                    vec![dir("/p/a/out", 10, now - 90 * DAY, vec![])],
+                ),
+                dir("/p/b", 20, now - DAY, vec![touched]),
+            ],
+        );
+
+        let report = test_rules(&root, &rules);
+        let matches = |name: &str| -> Vec<String> {
+            let rule = report.rules.iter().find(|r| r.rule == name).unwrap();
+            assert!(!rule.is_builtin);
+            rule.matches.iter().map(|m| m.path.clone()).collect()
+        };
+        assert_eq!(matches("Old output"), vec!["/p/a/out"]);
+        assert_eq!(matches("Any output"), vec!["/p/b/out", "/p/a/out"]);
+        assert!(report.rules.iter().any(|r| r.is_builtin));
+    }
+}
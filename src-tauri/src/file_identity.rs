@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+/// Where a file's data lives: the same for every hard link to it, and for a
+/// directory reached through a followed symlink or junction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileIdentity {
+    /// Device number on Unix, volume serial number on Windows
+    pub volume: u64,
+    /// Inode number on Unix, 128-bit NTFS/ReFS file id on Windows
+    pub index: u128,
+    /// Number of hard links to the file, if known
+    pub links: Option<u64>,
+}
+
+impl FileIdentity {
+    pub fn key(&self) -> (u64, u128) {
+        (self.volume, self.index)
+    }
+
+    /// Whether the file may be reachable by another path and has to be deduplicated
+    pub fn may_be_shared(&self) -> bool {
+        self.links.is_none_or(|links| links > 1)
+    }
+}
+
+/// Looks up file identities for the scanner's hard-link and cycle checks
+pub trait IdentitySource: Send + Sync {
+    /// Identity of the entry at `path`, following it only if `metadata` was read
+    /// through the link. `None` where the filesystem has no stable file ids,
+    /// e.g. FAT volumes.
+    fn identify(&self, path: &Path, metadata: &fs::Metadata) -> Option<FileIdentity>;
+
+    /// Identities of the entries of `dir` by name, without following them, where
+    /// they come cheaper from the listing than from `identify` per entry
+    fn list(&self, _dir: &Path) -> HashMap<OsString, FileIdentity> {
+        HashMap::new()
+    }
+}
+
+/// Identities from the host platform's filesystem
+pub struct PlatformIdentities;
+
+#[cfg(unix)]
+impl IdentitySource for PlatformIdentities {
+    fn identify(&self, _path: &Path, metadata: &fs::Metadata) -> Option<FileIdentity> {
+        use std::os::unix::fs::MetadataExt;
+        Some(FileIdentity {
+            volume: metadata.dev(),
+            index: u128::from(metadata.ino()),
+            links: Some(metadata.nlink()),
+        })
+    }
+}
+
+#[cfg(windows)]
+impl IdentitySource for PlatformIdentities {
+    fn identify(&self, path: &Path, metadata: &fs::Metadata) -> Option<FileIdentity> {
+        use windows_sys::Win32::Storage::FileSystem::{FileStandardInfo, FILE_STANDARD_INFO};
+
+        // A link's own metadata means the link itself is the entry, so its
+        // target mustn't take the link's place in the hard-link records
+        let file = windows::open(path, !metadata.is_symlink())?;
+        let id = windows::file_id(&file)?;
+        let standard: FILE_STANDARD_INFO = windows::query(&file, FileStandardInfo)?;
+        Some(FileIdentity {
+            volume: id.VolumeSerialNumber,
+            index: u128::from_le_bytes(id.FileId.Identifier),
+            links: Some(u64::from(standard.NumberOfLinks)),
+        })
+    }
+
+    /// One directory handle yields every entry's id, where `identify` would
+    /// open each file. Link counts aren't listed, so every file is deduplicated.
+    fn list(&self, dir: &Path) -> HashMap<OsString, FileIdentity> {
+        windows::list(dir).unwrap_or_default()
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::FileIdentity;
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::fs;
+    use std::os::windows::ffi::OsStringExt;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FileIdExtdDirectoryInfo, FileIdInfo, GetFileInformationByHandleEx,
+        FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT, FILE_ID_EXTD_DIR_INFO,
+        FILE_ID_INFO, FILE_INFO_BY_HANDLE_CLASS,
+    };
+
+    /// Directory entries read per call when listing
+    const LIST_BUFFER_BYTES: usize = 64 * 1024;
+
+    /// Open `path` with the flags std uses for metadata(): querying needs no
+    /// access rights, and backup semantics lets directories be opened
+    pub fn open(path: &Path, follow: bool) -> Option<fs::File> {
+        let mut flags = FILE_FLAG_BACKUP_SEMANTICS;
+        if !follow {
+            flags |= FILE_FLAG_OPEN_REPARSE_POINT;
+        }
+        fs::OpenOptions::new()
+            .access_mode(0)
+            .custom_flags(flags)
+            .open(path)
+            .ok()
+    }
+
+    /// Fixed-size information about an open file
+    pub fn query<T>(file: &fs::File, class: FILE_INFO_BY_HANDLE_CLASS) -> Option<T> {
+        let mut info = std::mem::MaybeUninit::<T>::uninit();
+        // SAFETY: the handle stays open for the call and info has room for a T,
+        // which is the struct `class` fills in
+        let ok = unsafe {
+            GetFileInformationByHandleEx(
+                file.as_raw_handle() as _,
+                class,
+                info.as_mut_ptr().cast(),
+                std::mem::size_of::<T>() as u32,
+            )
+        };
+        // SAFETY: the call succeeded, so it wrote a complete T
+        (ok != 0).then(|| unsafe { info.assume_init() })
+    }
+
+    pub fn file_id(file: &fs::File) -> Option<FILE_ID_INFO> {
+        query(file, FileIdInfo)
+    }
+
+    pub fn list(dir: &Path) -> Option<HashMap<OsString, FileIdentity>> {
+        let handle = open(dir, true)?;
+        let volume = file_id(&handle)?.VolumeSerialNumber;
+
+        let mut entries = HashMap::new();
+        // u64s keep the buffer aligned for FILE_ID_EXTD_DIR_INFO
+        let mut buffer = vec![0u64; LIST_BUFFER_BYTES / 8];
+        loop {
+            // SAFETY: the handle stays open and the buffer is writable for its full length
+            let ok = unsafe {
+                GetFileInformationByHandleEx(
+                    handle.as_raw_handle() as _,
+                    FileIdExtdDirectoryInfo,
+                    buffer.as_mut_ptr().cast(),
+                    LIST_BUFFER_BYTES as u32,
+                )
+            };
+            if ok == 0 {
+                // ERROR_NO_MORE_FILES once every entry has been returned
+                break;
+            }
+
+            let mut offset = 0;
+            loop {
+                // SAFETY: the call filled the buffer with a chain of entries, each
+                // starting at an aligned offset given by the previous one
+                let entry = unsafe {
+                    &*buffer
+                        .as_ptr()
+                        .cast::<u8>()
+                        .add(offset)
+                        .cast::<FILE_ID_EXTD_DIR_INFO>()
+                };
+                // SAFETY: FileName holds FileNameLength bytes of UTF-16 inside the buffer
+                let name = unsafe {
+                    std::slice::from_raw_parts(
+                        entry.FileName.as_ptr(),
+                        entry.FileNameLength as usize / 2,
+                    )
+                };
+                entries.insert(
+                    OsString::from_wide(name),
+                    FileIdentity {
+                        volume,
+                        index: u128::from_le_bytes(entry.FileId.Identifier),
+                        links: None,
+                    },
+                );
+                if entry.NextEntryOffset == 0 {
+                    break;
+                }
+                offset += entry.NextEntryOffset as usize;
+            }
+        }
+        Some(entries)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+impl IdentitySource for PlatformIdentities {
+    fn identify(&self, _path: &Path, _metadata: &fs::Metadata) -> Option<FileIdentity> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::ScannerCore;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    /// Hands out made-up identities by path relative to a root, standing in for
+    /// Windows file ids (and junctions) that can't be created on Linux
+    struct FakeIdentities {
+        root: PathBuf,
+        ids: HashMap<PathBuf, FileIdentity>,
+        /// Answer `list` for these directories instead of `identify` per entry
+        listed_dirs: Vec<PathBuf>,
+        lookups: AtomicUsize,
+    }
+
+    impl FakeIdentities {
+        fn new(root: &Path) -> Self {
+            Self {
+                root: root.to_path_buf(),
+                ids: HashMap::new(),
+                listed_dirs: Vec::new(),
+                lookups: AtomicUsize::new(0),
+            }
+        }
+
+        fn with(mut self, relative: &str, index: u128, links: Option<u64>) -> Self {
+            let identity = FileIdentity {
+                volume: 0xC0FFEE,
+                index,
+                links,
+            };
+            self.ids.insert(PathBuf::from(relative), identity);
+            self
+        }
+
+        fn listing(mut self, relative: &str) -> Self {
+            self.listed_dirs.push(PathBuf::from(relative));
+            self
+        }
+    }
+
+    impl IdentitySource for FakeIdentities {
+        fn identify(&self, path: &Path, _metadata: &fs::Metadata) -> Option<FileIdentity> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            self.ids.get(path.strip_prefix(&self.root).ok()?).copied()
+        }
+
+        fn list(&self, dir: &Path) -> HashMap<OsString, FileIdentity> {
+            let Ok(relative) = dir.strip_prefix(&self.root) else {
+                return HashMap::new();
+            };
+            if !self.listed_dirs.iter().any(|listed| listed == relative) {
+                return HashMap::new();
+            }
+            self.ids
+                .iter()
+                .filter(|(path, _)| path.parent() == Some(relative))
+                .map(|(path, id)| (path.file_name().unwrap().to_os_string(), *id))
+                .collect()
+        }
+    }
+
+    fn tree(files: &[&str]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, vec![1u8; 64 * 1024]).unwrap();
+        }
+        dir
+    }
+
+    fn scan_with(dir: &TempDir, identities: Arc<FakeIdentities>) -> u64 {
+        let core = ScannerCore::default().with_identities(identities);
+        core.scan_directory(dir.path()).expect("scan root").size
+    }
+
+    /// Total size of a scan of `dir`, and the size one of its files counts for
+    fn scan(dir: &TempDir, identities: FakeIdentities) -> (u64, u64) {
+        (scan_with(dir, Arc::new(identities)), single_file_size())
+    }
+
+    fn single_file_size() -> u64 {
+        let dir = tree(&["f"]);
+        ScannerCore::default()
+            .scan_directory(dir.path())
+            .unwrap()
+            .size
+    }
+
+    #[test]
+    fn hard_links_are_counted_once() {
+        let dir = tree(&["a/f", "b/f", "c/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("a/f", 7, Some(2))
+            .with("b/f", 7, Some(2))
+            .with("c/f", 8, Some(1));
+        let (total, file) = scan(&dir, ids);
+        assert_eq!(total, 2 * file);
+    }
+
+    #[test]
+    fn distinct_files_with_links_are_counted_separately() {
+        let dir = tree(&["a/f", "b/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("a/f", 7, Some(2))
+            .with("b/f", 8, Some(2));
+        let (total, file) = scan(&dir, ids);
+        assert_eq!(total, 2 * file);
+    }
+
+    #[test]
+    fn ids_differing_above_64_bits_are_distinct() {
+        // ReFS file ids use all 128 bits
+        let dir = tree(&["a/f", "b/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("a/f", 7, Some(2))
+            .with("b/f", (1 << 64) | 7, Some(2));
+        let (total, file) = scan(&dir, ids);
+        assert_eq!(total, 2 * file);
+    }
+
+    #[test]
+    fn shared_index_is_ignored_for_single_link_files() {
+        // Only files with several links are tracked, as on Unix
+        let dir = tree(&["a/f", "b/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("a/f", 7, Some(1))
+            .with("b/f", 7, Some(1));
+        let (total, file) = scan(&dir, ids);
+        assert_eq!(total, 2 * file);
+    }
+
+    #[test]
+    fn files_with_unknown_link_counts_are_always_tracked() {
+        let dir = tree(&["a/f", "b/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("a/f", 7, None)
+            .with("b/f", 7, None);
+        let (total, file) = scan(&dir, ids);
+        assert_eq!(total, file);
+    }
+
+    #[test]
+    fn files_without_identity_are_always_counted() {
+        let dir = tree(&["a/f", "b/f"]);
+        let (total, file) = scan(&dir, FakeIdentities::new(dir.path()));
+        assert_eq!(total, 2 * file);
+    }
+
+    #[test]
+    fn listed_identities_spare_per_entry_lookups() {
+        let dir = tree(&["a/f", "a/g", "a/h"]);
+        let ids = Arc::new(
+            FakeIdentities::new(dir.path())
+                .with("a", 1, Some(1))
+                .with("a/f", 7, None)
+                .with("a/g", 7, None)
+                .with("a/h", 8, None)
+                .listing("")
+                .listing("a"),
+        );
+        let total = scan_with(&dir, ids.clone());
+        assert_eq!(total, 2 * single_file_size());
+        // Only the root, which no listing covers, is looked up on its own
+        assert_eq!(ids.lookups.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn junction_back_to_an_ancestor_is_not_descended() {
+        // a/loop resolves to the same directory as a, like a junction pointing upwards
+        let dir = tree(&["a/f", "a/loop/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("", 1, Some(1))
+            .with("a", 2, Some(1))
+            .with("a/loop", 2, Some(1));
+        let (total, file) = scan(&dir, ids);
+        assert_eq!(total, file);
+    }
+
+    #[test]
+    fn directory_reached_twice_is_scanned_once() {
+        // b is a junction to a; whichever is reached second comes back empty
+        let dir = tree(&["a/f", "b/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("a", 2, Some(1))
+            .with("b", 2, Some(1));
+        let (total, file) = scan(&dir, ids);
+        assert_eq!(total, file);
+    }
+
+    #[test]
+    fn rescans_start_with_fresh_tracking() {
+        let dir = tree(&["a/f", "b/f"]);
+        let ids = FakeIdentities::new(dir.path())
+            .with("a/f", 7, Some(2))
+            .with("b/f", 7, Some(2));
+        let core = ScannerCore::default().with_identities(Arc::new(ids));
+        let first = core.scan_directory(dir.path()).unwrap().size;
+        let second = core.scan_directory(dir.path()).unwrap().size;
+        assert!(first > 0);
+        assert_eq!(first, second);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn platform_identities_dedupe_real_hard_links() {
+        let dir = tree(&["a/f"]);
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::hard_link(dir.path().join("a/f"), dir.path().join("b/f")).unwrap();
+        let total = ScannerCore::default()
+            .scan_directory(dir.path())
+            .unwrap()
+            .size;
+        assert_eq!(total, single_file_size());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unfollowed_link_does_not_take_its_targets_place() {
+        let dir = tree(&["a/f"]);
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::hard_link(dir.path().join("a/f"), dir.path().join("b/f")).unwrap();
+        let without_link = ScannerCore::default()
+            .scan_directory(dir.path())
+            .unwrap()
+            .size;
+
+        let link = dir.path().join("c");
+        std::os::unix::fs::symlink(dir.path().join("a/f"), &link).unwrap();
+        let with_link = ScannerCore::default()
+            .scan_directory(dir.path())
+            .unwrap()
+            .size;
+        let link_size = crate::scanner::allocated_size(&fs::symlink_metadata(&link).unwrap());
+        assert_eq!(with_link, without_link + link_size);
+    }
+}
@@ -1,26 +1,31 @@
+use crate::preview::FilePreview;
+use std::path::Path;
 use std::process::Command;
 
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-use std::path::Path;
+use std::ffi::OsStr;
 
-#[tauri::command]
-pub async fn file_preview(_path: String) -> Result<(), String> {
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("qlmanage")
-            .args(["-p", &_path])
-            .spawn()
-            .map_err(|e| format!("Failed to preview file: {}", e))?;
-        Ok(())
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        Err("Preview is only supported on macOS".to_string())
+/// Reject paths of synthetic nodes, such as summaries of merged entries, which
+/// contain a NUL that no path on disk can
+fn check_real_path(path: &str) -> Result<(), String> {
+    if path.contains('\0') {
+        return Err("Not a file on disk".to_string());
     }
+    Ok(())
+}
+
+/// Returns a preview of the file for the frontend to render
+#[tauri::command]
+pub async fn file_preview(path: String) -> Result<FilePreview, String> {
+    check_real_path(&path)?;
+    tokio::task::spawn_blocking(move || crate::preview::preview_file(Path::new(&path)))
+        .await
+        .map_err(|e| format!("Task failed: {}", e))?
 }
 
 #[tauri::command]
 pub async fn file_open(path: String) -> Result<(), String> {
+    check_real_path(&path)?;
     #[cfg(target_os = "macos")]
     {
         Command::new("open")
@@ -39,18 +44,215 @@ pub async fn file_open(path: String) -> Result<(), String> {
     }
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     {
-        let parent = Path::new(&path)
-            .parent()
-            .ok_or("Failed to get parent directory")?;
-        Command::new("xdg-open")
-            .arg(parent)
-            .spawn()
-            .map_err(|e| format!("Failed to open file manager: {}", e))?;
-        Ok(())
+        tokio::task::spawn_blocking(move || reveal_in_file_manager(Path::new(&path)))
+            .await
+            .map_err(|e| format!("Task failed: {}", e))?
+    }
+}
+
+/// File managers that accept a flag to select the given path, tried in order
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const SELECTING_FILE_MANAGERS: &[(&str, &[&str])] = &[
+    ("nautilus", &["--select"]),
+    ("dolphin", &["--select"]),
+    ("caja", &["--select"]),
+    ("nemo", &[]),
+];
+
+/// Reveal `path` with the freedesktop FileManager1 D-Bus interface on the session
+/// bus, falling back to known file managers and finally to opening the parent
+/// directory without a selection
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal_in_file_manager(path: &Path) -> Result<(), String> {
+    let show_items = || {
+        let connection = zbus::blocking::Connection::session().map_err(|e| e.to_string())?;
+        show_items_dbus(&connection, path)
+    };
+    reveal_with(path, show_items, |program, args| {
+        Command::new(program).args(args).spawn().map(drop)
+    })
+}
+
+/// The fallback chain of `reveal_in_file_manager`, with the D-Bus call and
+/// process spawning passed in
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal_with(
+    path: &Path,
+    show_items: impl FnOnce() -> Result<(), String>,
+    mut spawn: impl FnMut(&str, &[&OsStr]) -> std::io::Result<()>,
+) -> Result<(), String> {
+    if show_items().is_ok() {
+        return Ok(());
+    }
+
+    for (program, args) in SELECTING_FILE_MANAGERS {
+        let mut argv: Vec<&OsStr> = args.iter().map(OsStr::new).collect();
+        argv.push(path.as_os_str());
+        // Spawning fails with NotFound when the file manager isn't installed
+        if spawn(program, &argv).is_ok() {
+            return Ok(());
+        }
     }
+
+    let parent = path.parent().ok_or("Failed to get parent directory")?;
+    spawn("xdg-open", &[parent.as_os_str()])
+        .map_err(|e| format!("Failed to open file manager: {}", e))
+}
+
+/// Ask the file manager owning FileManager1 on `connection` to show `path`
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn show_items_dbus(connection: &zbus::blocking::Connection, path: &Path) -> Result<(), String> {
+    let uri = url::Url::from_file_path(path)
+        .map_err(|_| "Path must be absolute".to_string())?
+        .to_string();
+    connection
+        .call_method(
+            Some("org.freedesktop.FileManager1"),
+            "/org/freedesktop/FileManager1",
+            Some("org.freedesktop.FileManager1"),
+            "ShowItems",
+            &(vec![uri], ""),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn file_delete(path: String) -> Result<(), String> {
+    check_real_path(&path)?;
     trash::delete(&path).map_err(|e| format!("Failed to move to trash: {}", e))
 }
+
+#[cfg(all(test, not(any(target_os = "macos", target_os = "windows"))))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc;
+    use zbus::blocking::connection::Builder;
+
+    /// Stand-in for a file manager implementing FileManager1
+    struct FakeFileManager {
+        shown: mpsc::Sender<Vec<String>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.FileManager1")]
+    impl FakeFileManager {
+        fn show_items(&self, uris: Vec<String>, _startup_id: String) {
+            let _ = self.shown.send(uris);
+        }
+    }
+
+    /// Client and server ends of a peer-to-peer D-Bus connection, with the server
+    /// exporting `file_manager` if given
+    fn peer_bus(
+        file_manager: Option<FakeFileManager>,
+    ) -> (zbus::blocking::Connection, zbus::blocking::Connection) {
+        // Something is always exported so the object server runs from the start
+        // and answers calls to a missing object with an error
+        let (object_path, file_manager) = match file_manager {
+            Some(file_manager) => ("/org/freedesktop/FileManager1", file_manager),
+            None => {
+                let (shown, _) = mpsc::channel();
+                ("/elsewhere", FakeFileManager { shown })
+            }
+        };
+        let (client, server) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || {
+            Builder::unix_stream(server)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at(object_path, file_manager)
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        let client = Builder::unix_stream(client).p2p().build().unwrap();
+        (client, server.join().unwrap())
+    }
+
+    #[test]
+    fn show_items_sends_a_file_uri() {
+        let (shown, received) = mpsc::channel();
+        let (connection, _server) = peer_bus(Some(FakeFileManager { shown }));
+
+        show_items_dbus(&connection, Path::new("/home/me/My Files/report #1.pdf")).unwrap();
+        let uris = received
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(uris, ["file:///home/me/My%20Files/report%20%231.pdf"]);
+    }
+
+    #[test]
+    fn show_items_rejects_relative_paths() {
+        let (connection, _server) = peer_bus(None);
+        assert!(show_items_dbus(&connection, Path::new("relative/file")).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_file_managers_then_the_parent_directory() {
+        let (connection, _server) = peer_bus(None);
+        let path = Path::new("/data/file.txt");
+        let mut spawned = Vec::new();
+        let result = reveal_with(
+            path,
+            || show_items_dbus(&connection, path),
+            |program, args| {
+                spawned.push(format!("{} {:?}", program, args));
+                if program == "xdg-open" {
+                    Ok(())
+                } else {
+                    Err(std::io::ErrorKind::NotFound.into())
+                }
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(
+            spawned,
+            [
+                r#"nautilus ["--select", "/data/file.txt"]"#,
+                r#"dolphin ["--select", "/data/file.txt"]"#,
+                r#"caja ["--select", "/data/file.txt"]"#,
+                r#"nemo ["/data/file.txt"]"#,
+                r#"xdg-open ["/data"]"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_the_first_file_manager_that_starts() {
+        let mut spawned = Vec::new();
+        let result = reveal_with(
+            Path::new("/data/file.txt"),
+            || Err("no session bus".to_string()),
+            |program, _| {
+                spawned.push(program.to_string());
+                match program {
+                    "dolphin" => Ok(()),
+                    _ => Err(std::io::ErrorKind::NotFound.into()),
+                }
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(spawned, ["nautilus", "dolphin"]);
+    }
+
+    #[test]
+    fn d_bus_success_spawns_nothing() {
+        let (shown, _received) = mpsc::channel();
+        let (connection, _server) = peer_bus(Some(FakeFileManager { shown }));
+        let path = Path::new("/data/file.txt");
+        let result = reveal_with(
+            path,
+            || show_items_dbus(&connection, path),
+            |program, _| panic!("spawned {}", program),
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn synthetic_paths_are_rejected() {
+        assert!(check_real_path("/data/dir\0summary").is_err());
+        assert!(check_real_path("/data/dir/3 smaller items").is_ok());
+    }
+}
@@ -1,34 +1,134 @@
+pub mod age;
+pub mod alerts;
+pub mod checkpoint;
+pub mod cleanup;
+pub mod cleanup_rules;
+pub mod file_identity;
 pub mod file_ops;
+pub mod name_index;
+pub mod owners;
+pub mod preview;
+pub mod query;
 pub mod scanner;
+pub mod throttle;
+pub mod util;
+pub mod volume_monitor;
 pub mod volumes;
+pub mod watcher;
 
-use scanner::{DirNode, Scanner};
+use age::AgeHistogram;
+use alerts::{AlertManager, AlertThreshold};
+use cleanup::CleanupReport;
+use cleanup_rules::{RuleTestReport, RuleValidation};
+use name_index::{NameIndex, NameSearchResults};
+use owners::OwnerBreakdown;
+use query::{Query, SearchOptions, SearchResults};
+use scanner::{ChildrenOptions, ChildrenPage, DirNode, ScanConfig, ScanLimitsHit, Scanner};
+use serde::Serialize;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use tauri::{AppHandle, Manager, State};
+use volume_monitor::{MonitorConfig, VolumeMonitor};
+use watcher::ScanWatcher;
 
 struct AppState {
     scanner: Arc<Mutex<Option<Scanner>>>,
+    /// Bumped by every new scan, so that only the latest one stores its result
+    scan_generation: Arc<AtomicU64>,
+    /// Full tree of the last completed scan, kept for backend queries and live updates
+    scan_result: Arc<RwLock<Option<DirNode>>>,
+    /// Limits that `scan_result` stopped at
+    scan_limits: Arc<Mutex<ScanLimitsHit>>,
+    /// Name index over `scan_result`, rebuilt on demand after live updates
+    name_index: Arc<RwLock<Option<NameIndex>>>,
+    watcher: Arc<Mutex<Option<ScanWatcher>>>,
+    volume_monitor: Arc<Mutex<Option<VolumeMonitor>>>,
+    alerts: Arc<Mutex<AlertManager>>,
+}
+
+impl AppState {
+    /// Attach the stored scan's limits to a result computed from it
+    fn with_scan_limits<T>(&self, result: T) -> FromScan<T> {
+        FromScan {
+            result,
+            limits_hit: *self.scan_limits.lock().expect("Scan limits lock poisoned"),
+        }
+    }
+}
+
+/// A command result computed from the stored scan tree
+#[derive(Debug, Serialize)]
+struct FromScan<T> {
+    #[serde(flatten)]
+    result: T,
+    /// Entries below a limit the scan stopped at are missing from `result`
+    limits_hit: ScanLimitsHit,
 }
 
 #[tauri::command]
 async fn scan_directory(
     path: String,
+    config: Option<ScanConfig>,
     app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let scanner = Scanner::new(app.clone());
+    let scanner = Scanner::with_config(app.clone(), &config.unwrap_or_default())?;
 
-    // Store scanner for cancellation
-    {
-        let mut scanner_lock = state.scanner.lock().expect("Scanner lock poisoned");
-        *scanner_lock = Some(scanner.clone());
+    // Store scanner for cancellation. A scan still running would otherwise keep
+    // going and store its result over this one's when it finishes.
+    let previous = state
+        .scanner
+        .lock()
+        .expect("Scanner lock poisoned")
+        .replace(scanner.clone());
+    if let Some(previous) = previous {
+        previous.supersede();
     }
 
+    // A new scan invalidates the previous result and anything watching it
+    let watcher = state.watcher.lock().expect("Watcher lock poisoned").take();
+    stop_watcher(watcher).await?;
+    let generation = {
+        let mut scan_result = state
+            .scan_result
+            .write()
+            .expect("Scan result lock poisoned");
+        *scan_result = None;
+        *state.name_index.write().expect("Name index lock poisoned") = None;
+        state.scan_generation.fetch_add(1, Ordering::SeqCst) + 1
+    };
+
     // Run scan in background on blocking thread pool
+    let scan_result = state.scan_result.clone();
+    let scan_limits = state.scan_limits.clone();
+    let name_index = state.name_index.clone();
+    let scan_generation = state.scan_generation.clone();
     tokio::task::spawn_blocking(move || {
-        if let Err(e) = scanner.scan_directory(path.clone()) {
+        // Checked under the result lock, which a newer scan takes to move the
+        // generation on, so a superseded result is never stored
+        let is_current = || scan_generation.load(Ordering::SeqCst) == generation;
+        let published = scanner.scan_directory(path, |root| {
+            let mut result = scan_result.write().expect("Scan result lock poisoned");
+            if is_current() {
+                *scan_limits.lock().expect("Scan limits lock poisoned") = scanner.limits_hit();
+                *result = Some(root);
+            }
+        });
+        if let Err(e) = published {
             eprintln!("Scan error: {}", e);
+            return;
+        }
+
+        // Indexed once the result is out, so the first search doesn't wait for it
+        let tree = scan_result.read().expect("Scan result lock poisoned");
+        if let Some(root) = tree.as_ref().filter(|_| is_current()) {
+            let index = NameIndex::build(root);
+            name_index
+                .write()
+                .expect("Name index lock poisoned")
+                .get_or_insert(index);
         }
     });
 
@@ -44,6 +144,233 @@ async fn cancel_scan(state: State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
+/// Hold the running scan at its next directory boundaries without losing progress
+#[tauri::command]
+async fn pause_scan(state: State<'_, AppState>) -> Result<(), String> {
+    let scanner_lock = state.scanner.lock().expect("Scanner lock poisoned");
+    if let Some(scanner) = scanner_lock.as_ref() {
+        scanner.pause();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn resume_scan(state: State<'_, AppState>) -> Result<(), String> {
+    let scanner_lock = state.scanner.lock().expect("Scanner lock poisoned");
+    if let Some(scanner) = scanner_lock.as_ref() {
+        scanner.resume();
+    }
+    Ok(())
+}
+
+/// Keep the last completed scan up to date with filesystem changes
+#[tauri::command]
+async fn watch_scan(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    // Changes below a limit have no nodes to land in
+    if state
+        .scan_limits
+        .lock()
+        .expect("Scan limits lock poisoned")
+        .any()
+    {
+        return Err("The last scan stopped at a limit; run a full scan to watch it".to_string());
+    }
+    let mut watcher_lock = state.watcher.lock().expect("Watcher lock poisoned");
+    if watcher_lock.is_none() {
+        let scanner = state
+            .scanner
+            .lock()
+            .expect("Scanner lock poisoned")
+            .as_ref()
+            .map(Scanner::fork_core)
+            .ok_or("No completed scan to watch")?;
+        *watcher_lock = Some(ScanWatcher::start(
+            app,
+            state.scan_result.clone(),
+            state.name_index.clone(),
+            scanner,
+        )?);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn unwatch_scan(state: State<'_, AppState>) -> Result<(), String> {
+    let watcher = state.watcher.lock().expect("Watcher lock poisoned").take();
+    stop_watcher(watcher).await
+}
+
+/// Stop a watcher taken out of the state, off the async runtime and without
+/// holding the lock, since joining waits for its current batch to finish
+async fn stop_watcher(watcher: Option<ScanWatcher>) -> Result<(), String> {
+    let Some(mut watcher) = watcher else {
+        return Ok(());
+    };
+    tokio::task::spawn_blocking(move || watcher.stop())
+        .await
+        .map_err(|e| format!("Task failed: {}", e))
+}
+
+/// Find regenerable directories (dependencies, build output, caches) in the last scan
+#[tauri::command]
+async fn cleanup_suggestions(
+    state: State<'_, AppState>,
+) -> Result<FromScan<CleanupReport>, String> {
+    let scan_result = state.scan_result.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let tree = scan_result.read().expect("Scan result lock poisoned");
+        let root = tree.as_ref().ok_or("No completed scan")?;
+
+        // User rules come first so they take precedence over overlapping built-ins
+        let loaded = load_user_rules(None)?;
+        let mut rules = loaded.rules;
+        rules.extend(cleanup::builtin_rules());
+
+        let mut report = cleanup::find_candidates(root, &rules);
+        report.rule_errors = loaded.errors;
+        Ok::<_, String>(report)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+    Ok(state.with_scan_limits(report))
+}
+
+/// Bytes per modification-age bucket for a directory in the last scan
+#[tauri::command]
+async fn age_histogram(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<FromScan<AgeHistogram>, String> {
+    let scan_result = state.scan_result.clone();
+    let histogram = tokio::task::spawn_blocking(move || {
+        let tree = scan_result.read().expect("Scan result lock poisoned");
+        let root = tree.as_ref().ok_or("No completed scan")?;
+        let node = root
+            .find(&PathBuf::from(&path))
+            .ok_or("Path is not part of the last scan")?;
+        Ok::<_, String>(age::age_histogram(node, scanner::now_secs()))
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+    Ok(state.with_scan_limits(histogram))
+}
+
+/// Per-user and per-group usage for the scan root, or for `path` within the last scan
+#[tauri::command]
+async fn owner_usage(
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<FromScan<OwnerBreakdown>, String> {
+    let scan_result = state.scan_result.clone();
+    let breakdown = tokio::task::spawn_blocking(move || {
+        let tree = scan_result.read().expect("Scan result lock poisoned");
+        let root = tree.as_ref().ok_or("No completed scan")?;
+        let node = match path {
+            Some(path) => root
+                .find(&PathBuf::from(&path))
+                .ok_or("Path is not part of the last scan")?,
+            None => root,
+        };
+        Ok::<_, String>(owners::owner_breakdown(node))
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+    Ok(state.with_scan_limits(breakdown))
+}
+
+/// Run a query (see `query.rs` for the syntax) against the last scan
+#[tauri::command]
+async fn search(
+    query: String,
+    options: Option<SearchOptions>,
+    state: State<'_, AppState>,
+) -> Result<FromScan<SearchResults>, String> {
+    let query = Query::parse(&query, scanner::now_secs())?;
+    let scan_result = state.scan_result.clone();
+    let results = tokio::task::spawn_blocking(move || {
+        let tree = scan_result.read().expect("Scan result lock poisoned");
+        let root = tree.as_ref().ok_or("No completed scan")?;
+        query::search(root, &query, &options.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+    Ok(state.with_scan_limits(results))
+}
+
+/// Find entries in the last scan by name, using the name index
+#[tauri::command]
+async fn search_names(
+    query: String,
+    fuzzy: Option<bool>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<FromScan<NameSearchResults>, String> {
+    let scan_result = state.scan_result.clone();
+    let name_index = state.name_index.clone();
+    let results = tokio::task::spawn_blocking(move || {
+        let fuzzy = fuzzy.unwrap_or(false);
+        if let Some(index) = name_index
+            .read()
+            .expect("Name index lock poisoned")
+            .as_ref()
+        {
+            return Ok(index.search(&query, fuzzy, limit));
+        }
+
+        // Build without the index lock so other searches aren't stalled. The tree
+        // stays read-locked until the index is stored, so changes to it, which
+        // clear the index afterwards, can't be overwritten by a stale one.
+        let tree = scan_result.read().expect("Scan result lock poisoned");
+        let index = NameIndex::build(tree.as_ref().ok_or("No completed scan")?);
+        let results = index.search(&query, fuzzy, limit);
+        name_index
+            .write()
+            .expect("Name index lock poisoned")
+            .get_or_insert(index);
+        Ok::<_, String>(results)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+    Ok(state.with_scan_limits(results))
+}
+
+/// Check a cleanup rules file, defaulting to the one in the config directory
+#[tauri::command]
+async fn validate_cleanup_rules(path: Option<String>) -> Result<RuleValidation, String> {
+    let path = rules_path(path)?;
+    tokio::task::spawn_blocking(move || cleanup_rules::validate_rules(&path))
+        .await
+        .map_err(|e| format!("Task failed: {}", e))
+}
+
+/// Evaluate each cleanup rule on its own against the last scan
+#[tauri::command]
+async fn test_cleanup_rules(
+    state: State<'_, AppState>,
+    path: Option<String>,
+) -> Result<FromScan<RuleTestReport>, String> {
+    let scan_result = state.scan_result.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let tree = scan_result.read().expect("Scan result lock poisoned");
+        let root = tree.as_ref().ok_or("No completed scan")?;
+        let loaded = load_user_rules(path)?;
+        Ok::<_, String>(cleanup_rules::test_rules(root, &loaded.rules))
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+    Ok(state.with_scan_limits(report))
+}
+
+fn rules_path(path: Option<String>) -> Result<PathBuf, String> {
+    path.map(PathBuf::from)
+        .or_else(cleanup_rules::default_rules_path)
+        .ok_or_else(|| "Could not determine config directory".to_string())
+}
+
+fn load_user_rules(path: Option<String>) -> Result<cleanup_rules::LoadedRules, String> {
+    rules_path(path).map(|path| cleanup_rules::load_rules(&path))
+}
+
 #[tauri::command]
 async fn get_home_dir() -> Result<String, String> {
     dirs::home_dir()
@@ -60,12 +387,128 @@ async fn pick_directory(app: AppHandle) -> Result<Option<String>, String> {
     Ok(result.map(|p| p.to_string()))
 }
 
+/// One page of a directory's children, from the last scan when it has them and
+/// from disk otherwise
 #[tauri::command]
-async fn load_children(path: String) -> Result<Vec<DirNode>, String> {
+async fn load_children(
+    path: String,
+    options: Option<ChildrenOptions>,
+    state: State<'_, AppState>,
+) -> Result<ChildrenPage, String> {
     let path_buf = PathBuf::from(&path);
-    tokio::task::spawn_blocking(move || scanner::load_children(&path_buf))
+    let options = options.unwrap_or_default();
+    let scan_result = state.scan_result.clone();
+    tokio::task::spawn_blocking(move || {
+        {
+            let tree = scan_result.read().expect("Scan result lock poisoned");
+            let node = tree.as_ref().and_then(|root| root.find(&path_buf));
+            if let Some(page) = node.and_then(|node| scanner::children_page(node, &options)) {
+                return Ok(page);
+            }
+        }
+        scanner::load_children(&path_buf, &options)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+#[tauri::command]
+async fn start_volume_monitor(
+    interval_ms: Option<u64>,
+    threshold_bytes: Option<u64>,
+    include_all: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let defaults = MonitorConfig::default();
+    let config = MonitorConfig {
+        poll_interval: interval_ms
+            .map(|ms| Duration::from_millis(ms.max(volume_monitor::MIN_POLL_INTERVAL_MS)))
+            .unwrap_or(defaults.poll_interval),
+        change_threshold: threshold_bytes.unwrap_or(defaults.change_threshold),
+        include_all: include_all.unwrap_or(defaults.include_all),
+    };
+
+    // Restarting replaces the running monitor with the new configuration
+    let previous = state
+        .volume_monitor
+        .lock()
+        .expect("Volume monitor lock poisoned")
+        .take();
+    stop_monitor(previous).await?;
+
+    let replaced = state
+        .volume_monitor
+        .lock()
+        .expect("Volume monitor lock poisoned")
+        .replace(VolumeMonitor::start(app, config, state.alerts.clone()));
+    // Only set if another start ran while the previous monitor was stopping
+    stop_monitor(replaced).await
+}
+
+#[tauri::command]
+async fn stop_volume_monitor(state: State<'_, AppState>) -> Result<(), String> {
+    let monitor = state
+        .volume_monitor
+        .lock()
+        .expect("Volume monitor lock poisoned")
+        .take();
+    stop_monitor(monitor).await
+}
+
+/// Stop a monitor taken out of the state, off the async runtime and without
+/// holding the lock, since joining waits for its current poll to finish
+async fn stop_monitor(monitor: Option<VolumeMonitor>) -> Result<(), String> {
+    let Some(mut monitor) = monitor else {
+        return Ok(());
+    };
+    tokio::task::spawn_blocking(move || monitor.stop())
         .await
-        .map_err(|e| format!("Task failed: {}", e))?
+        .map_err(|e| format!("Task failed: {}", e))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VolumeAlertsStatus {
+    thresholds: Vec<AlertThreshold>,
+    active: Vec<String>,
+}
+
+/// Replace the low-space thresholds, starting the volume monitor if needed so they're evaluated
+#[tauri::command]
+async fn set_volume_alerts(
+    thresholds: Vec<AlertThreshold>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let has_thresholds = !thresholds.is_empty();
+    state
+        .alerts
+        .lock()
+        .expect("Alert manager lock poisoned")
+        .set_thresholds(thresholds)?;
+
+    let mut monitor_lock = state
+        .volume_monitor
+        .lock()
+        .expect("Volume monitor lock poisoned");
+    if has_thresholds && monitor_lock.is_none() {
+        *monitor_lock = Some(VolumeMonitor::start(
+            app,
+            MonitorConfig::default(),
+            state.alerts.clone(),
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_volume_alerts(state: State<'_, AppState>) -> Result<VolumeAlertsStatus, String> {
+    let alerts = state.alerts.lock().expect("Alert manager lock poisoned");
+    Ok(VolumeAlertsStatus {
+        thresholds: alerts.thresholds().to_vec(),
+        active: alerts.active(),
+    })
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -73,9 +516,17 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             let state = AppState {
                 scanner: Arc::new(Mutex::new(None)),
+                scan_generation: Arc::new(AtomicU64::new(0)),
+                scan_result: Arc::new(RwLock::new(None)),
+                scan_limits: Arc::new(Mutex::new(ScanLimitsHit::default())),
+                name_index: Arc::new(RwLock::new(None)),
+                watcher: Arc::new(Mutex::new(None)),
+                volume_monitor: Arc::new(Mutex::new(None)),
+                alerts: Arc::new(Mutex::new(AlertManager::default())),
             };
             app.manage(state);
             Ok(())
@@ -83,10 +534,25 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             scan_directory,
             cancel_scan,
+            pause_scan,
+            resume_scan,
+            watch_scan,
+            unwatch_scan,
+            cleanup_suggestions,
+            validate_cleanup_rules,
+            test_cleanup_rules,
+            age_histogram,
+            owner_usage,
+            search,
+            search_names,
             get_home_dir,
             pick_directory,
             load_children,
             volumes::list_volumes,
+            start_volume_monitor,
+            stop_volume_monitor,
+            set_volume_alerts,
+            get_volume_alerts,
             file_ops::file_preview,
             file_ops::file_open,
             file_ops::file_delete,
@@ -0,0 +1,441 @@
+use crate::scanner::DirNode;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Results returned when no limit is given
+pub const DEFAULT_NAME_SEARCH_LIMIT: usize = 100;
+/// Upper bound on results per search
+pub const MAX_NAME_SEARCH_LIMIT: usize = 1000;
+
+/// Minimum trigram similarity (shared / combined) for a fuzzy match
+const FUZZY_THRESHOLD: f64 = 0.25;
+
+/// Marks the root entry, which has no parent
+const NO_PARENT: u32 = u32::MAX;
+
+/// Sort key of a match, best first, followed by its entry id
+type Ranked = (Reverse<(u8, u32, u64)>, u32);
+
+struct IndexEntry {
+    /// End of the entry's name in `names`, which starts where the previous
+    /// entry's ends. The root entry holds the full scan root path instead.
+    name_end: usize,
+    /// End of the entry's lowercased name in `names_lower`
+    lower_end: usize,
+    /// Number of distinct padded trigrams, for fuzzy similarity
+    trigram_count: u16,
+    parent: u32,
+    size: u64,
+    item_count: u64,
+    is_file: bool,
+}
+
+/// Trigram index over every name in a scan tree. Entries store their parent
+/// instead of a full path, so memory grows with name length, not depth.
+pub struct NameIndex {
+    entries: Vec<IndexEntry>,
+    /// Every name back to back, in id order, so that scanning sorted candidate
+    /// ids reads memory in order instead of chasing one allocation per name
+    names: String,
+    names_lower: String,
+    /// Sorted entry ids for each trigram of the padded, lowercased names
+    trigrams: HashMap<u32, Vec<u32>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NameMatch {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub item_count: u64,
+    pub is_file: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NameSearchResults {
+    /// Number of matches before the limit was applied
+    pub total: usize,
+    pub items: Vec<NameMatch>,
+}
+
+impl NameIndex {
+    pub fn build(root: &DirNode) -> Self {
+        let mut index = NameIndex {
+            entries: Vec::new(),
+            names: String::new(),
+            names_lower: String::new(),
+            trigrams: HashMap::new(),
+        };
+
+        let mut stack = vec![(root, NO_PARENT)];
+        while let Some((node, parent)) = stack.pop() {
+            let id = index.entries.len() as u32;
+            let name = if parent == NO_PARENT {
+                &node.path
+            } else {
+                &node.name
+            };
+            let name_lower = node.name.to_lowercase();
+
+            // Ids only grow, so posting lists stay sorted without an extra pass
+            let unique = padded_trigrams(&name_lower);
+            let trigram_count = unique.len().min(u16::MAX as usize) as u16;
+            for trigram in unique {
+                index.trigrams.entry(trigram).or_default().push(id);
+            }
+
+            index.names.push_str(name);
+            index.names_lower.push_str(&name_lower);
+            index.entries.push(IndexEntry {
+                name_end: index.names.len(),
+                lower_end: index.names_lower.len(),
+                trigram_count,
+                parent,
+                size: node.size,
+                item_count: node.item_count,
+                is_file: node.is_file,
+            });
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|child| !child.is_summary())
+                    .map(|child| (child, id)),
+            );
+        }
+
+        index
+    }
+
+    fn name(&self, id: u32) -> &str {
+        let start = match id {
+            0 => 0,
+            _ => self.entries[id as usize - 1].name_end,
+        };
+        &self.names[start..self.entries[id as usize].name_end]
+    }
+
+    fn name_lower(&self, id: u32) -> &str {
+        let start = match id {
+            0 => 0,
+            _ => self.entries[id as usize - 1].lower_end,
+        };
+        &self.names_lower[start..self.entries[id as usize].lower_end]
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Case-insensitive substring search, or trigram-similarity search when `fuzzy`
+    /// is set. Exact and prefix name matches rank first, then larger entries.
+    pub fn search(&self, query: &str, fuzzy: bool, limit: Option<usize>) -> NameSearchResults {
+        let query = query.trim().to_lowercase();
+        let limit = limit
+            .unwrap_or(DEFAULT_NAME_SEARCH_LIMIT)
+            .min(MAX_NAME_SEARCH_LIMIT);
+        if query.is_empty() {
+            return NameSearchResults {
+                total: 0,
+                items: Vec::new(),
+            };
+        }
+
+        let query_trigrams: Vec<u32> = trigrams(&query)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let scored: Vec<(u32, u32)> = if fuzzy {
+            self.fuzzy_matches(&query)
+        } else {
+            self.substring_matches(&query, &query_trigrams)
+                .into_iter()
+                .map(|id| (id, 0))
+                .collect()
+        };
+
+        // Keys are computed once per match, as comparing them would otherwise
+        // reach into the entries every time; ids break ties
+        let mut ranked: Vec<Ranked> = scored
+            .into_par_iter()
+            .map(|(id, score)| {
+                let entry = &self.entries[id as usize];
+                let name_lower = self.name_lower(id);
+                let name_rank = if name_lower == query {
+                    2
+                } else if name_lower.starts_with(&query) {
+                    1
+                } else {
+                    0
+                };
+                (Reverse((name_rank, score, entry.size)), id)
+            })
+            .collect();
+
+        let total = ranked.len();
+        if ranked.len() > limit {
+            ranked.select_nth_unstable(limit);
+            ranked.truncate(limit);
+        }
+        ranked.sort_unstable();
+
+        NameSearchResults {
+            total,
+            items: ranked
+                .into_iter()
+                .map(|(_, id)| self.to_match(id))
+                .collect(),
+        }
+    }
+
+    fn substring_matches(&self, query: &str, query_trigrams: &[u32]) -> Vec<u32> {
+        // Queries shorter than a trigram can't use the index
+        if query_trigrams.is_empty() {
+            return (0..self.entries.len() as u32)
+                .filter(|&id| self.name_lower(id).contains(query))
+                .collect();
+        }
+
+        let mut lists: Vec<&Vec<u32>> = Vec::with_capacity(query_trigrams.len());
+        for trigram in query_trigrams {
+            match self.trigrams.get(trigram) {
+                Some(list) => lists.push(list),
+                None => return Vec::new(),
+            }
+        }
+        lists.sort_by_key(|list| list.len());
+
+        let mut candidates = lists[0].clone();
+        for list in &lists[1..] {
+            // Both are sorted, so one pass over each finds the common ids
+            let mut rest = list.iter().peekable();
+            candidates.retain(|id| {
+                while rest.next_if(|other| *other < id).is_some() {}
+                rest.peek() == Some(&id)
+            });
+        }
+        // Shared trigrams don't guarantee they're adjacent and in order. Common
+        // trigrams leave hundreds of thousands of candidates, so check in parallel.
+        candidates
+            .into_par_iter()
+            .filter(|&id| self.name_lower(id).contains(query))
+            .collect()
+    }
+
+    /// Entries whose padded trigram sets are similar to the query's (Jaccard index),
+    /// scored in thousandths. Padding lets short and transposed names still overlap.
+    fn fuzzy_matches(&self, query: &str) -> Vec<(u32, u32)> {
+        let query_trigrams = padded_trigrams(query);
+        // Counts per entry id; common trigrams hit so many entries that a
+        // dense array beats a hash map
+        let mut shared = vec![0u16; self.entries.len()];
+        let mut hit = Vec::new();
+        for trigram in &query_trigrams {
+            for &id in self.trigrams.get(trigram).into_iter().flatten() {
+                let count = &mut shared[id as usize];
+                if *count == 0 {
+                    hit.push(id);
+                }
+                *count = count.saturating_add(1);
+            }
+        }
+
+        hit.into_iter()
+            .filter_map(|id| {
+                let count = shared[id as usize] as u32;
+                let total = query_trigrams.len() as u32
+                    + self.entries[id as usize].trigram_count as u32
+                    - count;
+                let similarity = count as f64 / total as f64;
+                (similarity >= FUZZY_THRESHOLD).then_some((id, (similarity * 1000.0) as u32))
+            })
+            .collect()
+    }
+
+    fn to_match(&self, id: u32) -> NameMatch {
+        let entry = &self.entries[id as usize];
+
+        let mut names = Vec::new();
+        let mut current = id;
+        while current != NO_PARENT {
+            names.push(self.name(current));
+            current = self.entries[current as usize].parent;
+        }
+        let path: PathBuf = names.into_iter().rev().collect();
+
+        NameMatch {
+            name: if entry.parent == NO_PARENT {
+                path.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| self.name(id).to_string())
+            } else {
+                self.name(id).to_string()
+            },
+            path: path.to_string_lossy().to_string(),
+            size: entry.size,
+            item_count: entry.item_count,
+            is_file: entry.is_file,
+        }
+    }
+}
+
+/// Byte trigrams of a lowercased name, packed into a u32
+fn trigrams(name: &str) -> impl Iterator<Item = u32> + '_ {
+    name.as_bytes()
+        .windows(3)
+        .map(|w| (w[0] as u32) << 16 | (w[1] as u32) << 8 | w[2] as u32)
+}
+
+/// Distinct trigrams of the name padded with two leading spaces and one trailing,
+/// a superset of its plain trigrams that also marks where the name starts and ends
+fn padded_trigrams(name: &str) -> HashSet<u32> {
+    trigrams(&format!("  {} ", name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn file(path: &str, size: u64) -> DirNode {
+        serde_json::from_value(json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "size": size,
+            "item_count": 1,
+            "is_file": true,
+        }))
+        .unwrap()
+    }
+
+    fn dir(path: &str, children: Vec<DirNode>) -> DirNode {
+        let mut node: DirNode = serde_json::from_value(json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "size": 0,
+            "item_count": 0,
+            "is_file": false,
+        }))
+        .unwrap();
+        node.size = children.iter().map(|c| c.size).sum();
+        node.item_count = children.iter().map(|c| c.item_count).sum();
+        node.children = children;
+        node
+    }
+
+    fn index() -> NameIndex {
+        NameIndex::build(&dir(
+            "/data",
+            vec![
+                dir(
+                    "/data/Receipts",
+                    vec![
+                        file("/data/Receipts/receipt-2024.pdf", 300),
+                        file("/data/Receipts/old_receipt.pdf", 900),
+                    ],
+                ),
+                dir(
+                    "/data/photos",
+                    vec![
+                        file("/data/photos/IMG_0001.JPG", 5000),
+                        file("/data/photos/abc_bcd.txt", 10),
+                    ],
+                ),
+                file("/data/receipt", 1),
+            ],
+        ))
+    }
+
+    fn paths(results: &NameSearchResults) -> Vec<&str> {
+        results.items.iter().map(|m| m.path.as_str()).collect()
+    }
+
+    #[test]
+    fn every_node_is_indexed() {
+        assert_eq!(index().len(), 8);
+        assert!(!index().is_empty());
+    }
+
+    #[test]
+    fn substring_matches_ignore_case_and_rank_exact_then_prefix_then_size() {
+        let results = index().search("RECEIPT", false, None);
+        assert_eq!(results.total, 4);
+        assert_eq!(
+            paths(&results),
+            [
+                "/data/receipt",
+                "/data/Receipts",
+                "/data/Receipts/receipt-2024.pdf",
+                "/data/Receipts/old_receipt.pdf",
+            ]
+        );
+    }
+
+    #[test]
+    fn shared_trigrams_must_be_contiguous() {
+        // abc_bcd has both trigrams of "abcd" but not the substring
+        assert_eq!(index().search("abcd", false, None).total, 0);
+        assert_eq!(
+            paths(&index().search("c_b", false, None)),
+            ["/data/photos/abc_bcd.txt"]
+        );
+    }
+
+    #[test]
+    fn queries_shorter_than_a_trigram_scan_every_name() {
+        let results = index().search("jp", false, None);
+        assert_eq!(paths(&results), ["/data/photos/IMG_0001.JPG"]);
+    }
+
+    #[test]
+    fn unknown_trigrams_match_nothing() {
+        assert_eq!(index().search("zzz", false, None).total, 0);
+    }
+
+    #[test]
+    fn fuzzy_matches_tolerate_typos() {
+        let results = index().search("reciept", true, None);
+        assert!(paths(&results).contains(&"/data/receipt"));
+        assert!(!paths(&results).contains(&"/data/photos"));
+        assert_eq!(index().search("reciept", false, None).total, 0);
+    }
+
+    #[test]
+    fn fuzzy_matches_rank_closer_names_first() {
+        let results = index().search("receipts", true, None);
+        assert_eq!(results.items[0].path, "/data/Receipts");
+        assert_eq!(results.items[1].path, "/data/receipt");
+    }
+
+    #[test]
+    fn limits_keep_the_best_matches_and_the_total() {
+        let results = index().search("receipt", false, Some(2));
+        assert_eq!(results.total, 4);
+        assert_eq!(paths(&results), ["/data/receipt", "/data/Receipts"]);
+    }
+
+    #[test]
+    fn matches_carry_their_full_path() {
+        let results = index().search("img_0001", false, None);
+        let found = &results.items[0];
+        assert_eq!(found.name, "IMG_0001.JPG");
+        assert_eq!(found.path, "/data/photos/IMG_0001.JPG");
+        assert_eq!(found.size, 5000);
+        assert!(found.is_file);
+
+        let root = &index().search("data", false, None).items[0];
+        assert_eq!(root.name, "data");
+        assert_eq!(root.path, "/data");
+    }
+
+    #[test]
+    fn blank_queries_match_nothing() {
+        assert_eq!(index().search("  ", false, None).total, 0);
+    }
+}
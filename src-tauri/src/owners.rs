@@ -0,0 +1,256 @@
+use crate::scanner::DirNode;
+use crate::util::{group_name, user_name};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerUsage {
+    pub id: u32,
+    /// Name from the passwd/group database, absent for unknown ids
+    pub name: Option<String>,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+/// Disk usage of a subtree split by owning user and group
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerBreakdown {
+    pub path: String,
+    /// Sorted by size, largest first
+    pub users: Vec<OwnerUsage>,
+    pub groups: Vec<OwnerUsage>,
+}
+
+/// Bytes and file counts of the files in a subtree per owning user and group,
+/// kept on every directory node as it's scanned
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnerTotals {
+    /// Sorted by id
+    pub users: Vec<OwnerTotal>,
+    /// Sorted by id
+    pub groups: Vec<OwnerTotal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnerTotal {
+    pub id: u32,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+impl OwnerTotals {
+    /// Totals of the files among `children` and of the subtrees below the rest
+    pub fn of_children(children: &[DirNode]) -> Self {
+        let mut totals = Self::default();
+        for child in children.iter().filter(|c| !c.is_summary()) {
+            if child.is_file {
+                totals.add_file(child);
+            } else {
+                totals.merge(&child.owners);
+            }
+        }
+        totals
+    }
+
+    fn of_file(file: &DirNode) -> Self {
+        let mut totals = Self::default();
+        totals.add_file(file);
+        totals
+    }
+
+    fn add_file(&mut self, file: &DirNode) {
+        for (id, totals) in [(file.uid, &mut self.users), (file.gid, &mut self.groups)] {
+            if let Some(id) = id {
+                add(
+                    totals,
+                    OwnerTotal {
+                        id,
+                        size: file.size,
+                        file_count: 1,
+                    },
+                );
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &OwnerTotals) {
+        for &total in &other.users {
+            add(&mut self.users, total);
+        }
+        for &total in &other.groups {
+            add(&mut self.groups, total);
+        }
+    }
+}
+
+fn add(totals: &mut Vec<OwnerTotal>, total: OwnerTotal) {
+    match totals.binary_search_by_key(&total.id, |t| t.id) {
+        Ok(i) => {
+            totals[i].size += total.size;
+            totals[i].file_count += total.file_count;
+        }
+        Err(i) => totals.insert(i, total),
+    }
+}
+
+/// Breakdown of a node from the totals gathered while scanning, which also
+/// cover the nodes below a depth limit that the tree doesn't hold
+pub fn owner_breakdown(node: &DirNode) -> OwnerBreakdown {
+    let file_totals;
+    let totals = if node.is_file {
+        file_totals = OwnerTotals::of_file(node);
+        &file_totals
+    } else {
+        &node.owners
+    };
+
+    OwnerBreakdown {
+        path: node.path.clone(),
+        users: into_usage(&totals.users, user_name),
+        groups: into_usage(&totals.groups, group_name),
+    }
+}
+
+fn into_usage(totals: &[OwnerTotal], resolve: fn(u32) -> Option<String>) -> Vec<OwnerUsage> {
+    let mut usage: Vec<OwnerUsage> = totals
+        .iter()
+        .map(|total| OwnerUsage {
+            id: total.id,
+            name: resolve(total.id),
+            size: total.size,
+            file_count: total.file_count,
+        })
+        .collect();
+    usage.sort_by_key(|u| std::cmp::Reverse(u.size));
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn file(path: &str, size: u64, uid: u32, gid: u32) -> DirNode {
+        serde_json::from_value(json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "size": size,
+            "item_count": 1,
+            "is_file": true,
+            "uid": uid,
+            "gid": gid,
+        }))
+        .unwrap()
+    }
+
+    fn totals(entries: &[(u32, u64, u64)]) -> Vec<OwnerTotal> {
+        entries
+            .iter()
+            .map(|&(id, size, file_count)| OwnerTotal {
+                id,
+                size,
+                file_count,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn totals_merge_files_and_subtrees() {
+        let mut subdir: DirNode = serde_json::from_value(json!({
+            "name": "sub",
+            "path": "/p/sub",
+            "size": 0,
+            "item_count": 0,
+            "is_file": false,
+        }))
+        .unwrap();
+        subdir.owners = OwnerTotals {
+            users: totals(&[(1000, 50, 2), (2000, 5, 1)]),
+            groups: totals(&[(100, 55, 3)]),
+        };
+        let children = vec![file("/p/a", 10, 1000, 100), file("/p/b", 7, 0, 0), subdir];
+
+        let merged = OwnerTotals::of_children(&children);
+        assert_eq!(
+            merged.users,
+            totals(&[(0, 7, 1), (1000, 60, 3), (2000, 5, 1)])
+        );
+        assert_eq!(merged.groups, totals(&[(0, 7, 1), (100, 65, 4)]));
+    }
+
+    #[test]
+    fn breakdown_of_a_file_is_its_own_owner() {
+        let breakdown = owner_breakdown(&file("/p/a", 10, 1000, 100));
+        assert_eq!(breakdown.users.len(), 1);
+        assert_eq!(breakdown.users[0].id, 1000);
+        assert_eq!(breakdown.groups[0].size, 10);
+    }
+
+    #[cfg(unix)]
+    mod scanned {
+        use super::*;
+        use crate::scanner::{ScanConfig, ScannerCore};
+        use std::fs;
+        use std::path::Path;
+        use tempfile::TempDir;
+
+        /// Three levels of directories with two files each
+        fn tree() -> TempDir {
+            let root = TempDir::new().unwrap();
+            let mut dir = root.path().to_path_buf();
+            for level in 0..3 {
+                dir = dir.join(format!("level{}", level));
+                fs::create_dir(&dir).unwrap();
+                fs::write(dir.join("a"), vec![0u8; 4096]).unwrap();
+                fs::write(dir.join("b"), vec![0u8; 8192]).unwrap();
+            }
+            root
+        }
+
+        fn scan(root: &Path, max_depth: Option<usize>) -> DirNode {
+            let config = ScanConfig {
+                max_depth,
+                ..Default::default()
+            };
+            ScannerCore::with_config(&config)
+                .unwrap()
+                .scan_directory(root)
+                .unwrap()
+        }
+
+        #[test]
+        fn depth_limited_scans_keep_totals_below_the_limit() {
+            let root = tree();
+            let full = owner_breakdown(&scan(root.path(), None));
+            let limited_tree = scan(root.path(), Some(1));
+            assert!(limited_tree.children[0].children.is_empty());
+
+            let limited = owner_breakdown(&limited_tree);
+            assert_eq!(limited.users.len(), 1);
+            assert_eq!(limited.users[0].file_count, 6);
+            assert_eq!(limited.users[0].size, full.users[0].size);
+            assert_eq!(limited.groups[0].file_count, 6);
+        }
+
+        #[test]
+        fn replacing_a_node_updates_every_ancestor() {
+            let root = tree();
+            let mut scanned = scan(root.path(), None);
+            let path = root.path().join("level0/level1/a");
+            let mut changed = scanned.find(&path).unwrap().clone();
+            changed.uid = Some(54321);
+            let size = changed.size;
+            assert!(scanned.replace_descendant(&path, Some(changed), &mut |_| {}));
+
+            for ancestor in [root.path().join("level0/level1"), root.path().to_path_buf()] {
+                let breakdown = owner_breakdown(scanned.find(&ancestor).unwrap());
+                let moved = breakdown.users.iter().find(|u| u.id == 54321).unwrap();
+                assert_eq!((moved.size, moved.file_count), (size, 1));
+            }
+
+            assert!(scanned.replace_descendant(&path, None, &mut |_| {}));
+            let breakdown = owner_breakdown(&scanned);
+            assert!(breakdown.users.iter().all(|u| u.id != 54321));
+            assert_eq!(breakdown.users[0].file_count, 5);
+        }
+    }
+}
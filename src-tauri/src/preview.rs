@@ -0,0 +1,1120 @@
+use base64::Engine;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes read from the start of a file for text previews
+const TEXT_PREVIEW_BYTES: usize = 64 * 1024;
+/// Maximum edge length of generated image thumbnails
+const THUMBNAIL_SIZE: u32 = 256;
+/// Images larger than this are only probed for dimensions, not decoded
+const MAX_THUMBNAIL_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+/// Maximum number of archive entries included in a preview
+const MAX_ARCHIVE_ENTRIES: usize = 500;
+/// Bytes searched for the first MP3 frame after any ID3 tag
+const MP3_SYNC_SEARCH_BYTES: u64 = 8 * 1024;
+/// Bytes at the end of an Ogg file searched for the last page
+const OGG_TAIL_BYTES: u64 = 64 * 1024;
+
+/// Start of every Matroska/WebM file
+const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+const MKV_SEGMENT: u32 = 0x1853_8067;
+const MKV_INFO: u32 = 0x1549_A966;
+const MKV_TIMECODE_SCALE: u32 = 0x2A_D7B1;
+const MKV_DURATION: u32 = 0x4489;
+const MKV_TRACKS: u32 = 0x1654_AE6B;
+const MKV_TRACK_ENTRY: u32 = 0xAE;
+const MKV_AUDIO: u32 = 0xE1;
+const MKV_SAMPLING_FREQUENCY: u32 = 0xB5;
+const MKV_CHANNELS: u32 = 0x9F;
+
+/// MPEG-1 and MPEG-2/2.5 Layer III bitrates in kbit/s by header index
+const MP3_BITRATES: [[u32; 15]; 2] = [
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+/// MPEG-1 sample rates by header index; MPEG-2 halves and MPEG-2.5 quarters them
+const MP3_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FilePreview {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub mime: String,
+    #[serde(flatten)]
+    pub content: PreviewContent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PreviewContent {
+    Text {
+        encoding: String,
+        text: String,
+        truncated: bool,
+    },
+    Image {
+        width: u32,
+        height: u32,
+        /// PNG data URL, absent if the image is too large or can't be decoded
+        thumbnail: Option<String>,
+    },
+    Archive {
+        format: String,
+        entries: Vec<ArchiveEntry>,
+        total_entries: u64,
+        /// The archive wasn't read to the end, so it holds at least `total_entries`
+        total_is_lower_bound: bool,
+        truncated: bool,
+    },
+    Media {
+        duration_secs: Option<f64>,
+        sample_rate: Option<u32>,
+        channels: Option<u16>,
+    },
+    Binary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileKind {
+    Image,
+    Zip,
+    Tar,
+    TarGz,
+    Audio,
+    Video,
+    Other,
+}
+
+/// Build a preview for the file at `path`, choosing the renderer from its extension
+pub fn preview_file(path: &Path) -> Result<FilePreview, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Cannot read file: {}", e))?;
+    if metadata.is_dir() {
+        return Err("Path is a directory".to_string());
+    }
+
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    let (kind, mime) = classify(&name);
+
+    let content = match kind {
+        FileKind::Image => preview_image(path, metadata.len()),
+        FileKind::Zip => preview_zip(path),
+        FileKind::Tar => preview_tar(File::open(path).map_err(|e| e.to_string())?, "tar"),
+        FileKind::TarGz => preview_tar(
+            flate2::read::GzDecoder::new(File::open(path).map_err(|e| e.to_string())?),
+            "tar.gz",
+        ),
+        FileKind::Audio | FileKind::Video => Ok(preview_media(path)),
+        FileKind::Other => preview_text(path),
+    }
+    // Fall back to the text/binary sniffing if a specialised reader fails
+    .or_else(|_| preview_text(path))?;
+
+    let mime = match content {
+        PreviewContent::Text { .. } => "text/plain",
+        _ => mime,
+    };
+
+    Ok(FilePreview {
+        name,
+        path: path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        mime: mime.to_string(),
+        content,
+    })
+}
+
+fn classify(name: &str) -> (FileKind, &'static str) {
+    let lower = name.to_lowercase();
+    if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+        return (FileKind::TarGz, "application/gzip");
+    }
+
+    let ext = lower.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    match ext {
+        "png" => (FileKind::Image, "image/png"),
+        "jpg" | "jpeg" => (FileKind::Image, "image/jpeg"),
+        "gif" => (FileKind::Image, "image/gif"),
+        "webp" => (FileKind::Image, "image/webp"),
+        "bmp" => (FileKind::Image, "image/bmp"),
+        "zip" | "jar" => (FileKind::Zip, "application/zip"),
+        "tar" => (FileKind::Tar, "application/x-tar"),
+        "wav" => (FileKind::Audio, "audio/wav"),
+        "mp3" => (FileKind::Audio, "audio/mpeg"),
+        "flac" => (FileKind::Audio, "audio/flac"),
+        "ogg" => (FileKind::Audio, "audio/ogg"),
+        "m4a" => (FileKind::Audio, "audio/mp4"),
+        "mp4" | "m4v" => (FileKind::Video, "video/mp4"),
+        "mov" => (FileKind::Video, "video/quicktime"),
+        "mkv" => (FileKind::Video, "video/x-matroska"),
+        "webm" => (FileKind::Video, "video/webm"),
+        "avi" => (FileKind::Video, "video/x-msvideo"),
+        _ => (FileKind::Other, "application/octet-stream"),
+    }
+}
+
+fn preview_text(path: &Path) -> Result<PreviewContent, String> {
+    let mut file = File::open(path).map_err(|e| format!("Cannot open file: {}", e))?;
+    let mut head = Vec::with_capacity(TEXT_PREVIEW_BYTES);
+    file.by_ref()
+        .take(TEXT_PREVIEW_BYTES as u64 + 1)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("Cannot read file: {}", e))?;
+
+    let truncated = head.len() > TEXT_PREVIEW_BYTES;
+    head.truncate(TEXT_PREVIEW_BYTES);
+
+    // A BOM is authoritative; otherwise NUL bytes mean binary content
+    let encoding = match encoding_rs::Encoding::for_bom(&head) {
+        Some((encoding, _)) => encoding,
+        None if head.contains(&0) => return Ok(PreviewContent::Binary),
+        None if is_utf8_prefix(&head) => encoding_rs::UTF_8,
+        None => {
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(&head, !truncated);
+            detector.guess(None, true)
+        }
+    };
+
+    let (text, encoding, _) = encoding.decode(&head);
+    Ok(PreviewContent::Text {
+        encoding: encoding.name().to_string(),
+        text: text.into_owned(),
+        truncated,
+    })
+}
+
+/// UTF-8 check that tolerates a multi-byte sequence cut off by the read limit
+fn is_utf8_prefix(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+fn preview_image(path: &Path, size: u64) -> Result<PreviewContent, String> {
+    let reader = image::ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| e.to_string())?;
+    let (width, height) = reader.into_dimensions().map_err(|e| e.to_string())?;
+
+    let thumbnail = if size <= MAX_THUMBNAIL_SOURCE_BYTES {
+        render_thumbnail(path)
+    } else {
+        None
+    };
+
+    Ok(PreviewContent::Image {
+        width,
+        height,
+        thumbnail,
+    })
+}
+
+fn render_thumbnail(path: &Path) -> Option<String> {
+    let image = image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    thumbnail.write_to(&mut png, image::ImageFormat::Png).ok()?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(png.into_inner());
+    Some(format!("data:image/png;base64,{}", encoded))
+}
+
+fn preview_zip(path: &Path) -> Result<PreviewContent, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+
+    let total_entries = archive.len();
+    let mut entries = Vec::with_capacity(total_entries.min(MAX_ARCHIVE_ENTRIES));
+    for i in 0..total_entries.min(MAX_ARCHIVE_ENTRIES) {
+        let entry = archive.by_index_raw(i).map_err(|e| e.to_string())?;
+        entries.push(ArchiveEntry {
+            path: entry.name().to_string(),
+            size: entry.size(),
+            is_dir: entry.is_dir(),
+        });
+    }
+
+    Ok(PreviewContent::Archive {
+        format: "zip".to_string(),
+        entries,
+        total_entries: total_entries as u64,
+        total_is_lower_bound: false,
+        truncated: total_entries > MAX_ARCHIVE_ENTRIES,
+    })
+}
+
+fn preview_tar<R: Read>(reader: R, format: &str) -> Result<PreviewContent, String> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    let mut truncated = false;
+
+    // Tar has no central directory, and counting every entry would mean reading
+    // the whole (possibly compressed) stream, so stop once one more is seen
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if entries.len() == MAX_ARCHIVE_ENTRIES {
+            truncated = true;
+            break;
+        }
+        entries.push(ArchiveEntry {
+            path: entry
+                .path()
+                .map_err(|e| e.to_string())?
+                .to_string_lossy()
+                .to_string(),
+            size: entry.size(),
+            is_dir: entry.header().entry_type().is_dir(),
+        });
+    }
+
+    Ok(PreviewContent::Archive {
+        format: format.to_string(),
+        total_entries: entries.len() as u64 + u64::from(truncated),
+        total_is_lower_bound: truncated,
+        truncated,
+        entries,
+    })
+}
+
+/// Media metadata from container headers; fields stay empty for unsupported formats
+fn preview_media(path: &Path) -> PreviewContent {
+    let mut file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return empty_media(),
+    };
+
+    let mut header = [0u8; 12];
+    if file.read_exact(&mut header).is_err() {
+        return empty_media();
+    }
+
+    let metadata = if &header[0..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+        read_wav_metadata(&mut file)
+    } else if &header[4..8] == b"ftyp" {
+        read_mp4_metadata(&mut file)
+    } else if &header[0..4] == b"fLaC" {
+        read_flac_metadata(&mut file)
+    } else if &header[0..4] == b"OggS" {
+        read_ogg_metadata(&mut file)
+    } else if header[0..4] == EBML_MAGIC {
+        read_matroska_metadata(&mut file)
+    } else if &header[0..3] == b"ID3" || has_extension(path, "mp3") {
+        // MP3 has no magic of its own, so frames are only searched for in files
+        // that claim to be one
+        read_mp3_metadata(&mut file)
+    } else {
+        None
+    };
+    metadata.unwrap_or_else(empty_media)
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn empty_media() -> PreviewContent {
+    PreviewContent::Media {
+        duration_secs: None,
+        sample_rate: None,
+        channels: None,
+    }
+}
+
+/// Walk RIFF chunks after the WAVE header to find `fmt ` and `data`
+fn read_wav_metadata(file: &mut File) -> Option<PreviewContent> {
+    let mut channels = None;
+    let mut sample_rate = None;
+    let mut byte_rate = None;
+    let mut data_len = None;
+
+    file.seek(SeekFrom::Start(12)).ok()?;
+    let mut chunk = [0u8; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        let len = u32::from_le_bytes(chunk[4..8].try_into().ok()?) as u64;
+        match &chunk[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 16];
+                file.read_exact(&mut fmt).ok()?;
+                channels = Some(u16::from_le_bytes([fmt[2], fmt[3]]));
+                sample_rate = Some(u32::from_le_bytes(fmt[4..8].try_into().ok()?));
+                byte_rate = Some(u32::from_le_bytes(fmt[8..12].try_into().ok()?));
+                file.seek(SeekFrom::Current(len as i64 - 16 + (len & 1) as i64))
+                    .ok()?;
+            }
+            b"data" => {
+                data_len = Some(len);
+                break;
+            }
+            _ => {
+                // Chunks are padded to an even length
+                file.seek(SeekFrom::Current((len + (len & 1)) as i64))
+                    .ok()?;
+            }
+        }
+    }
+
+    let duration_secs = match (data_len, byte_rate) {
+        (Some(len), Some(rate)) if rate > 0 => Some(len as f64 / rate as f64),
+        _ => None,
+    };
+
+    Some(PreviewContent::Media {
+        duration_secs,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Find `moov/mvhd` and read the movie timescale and duration
+fn read_mp4_metadata(file: &mut File) -> Option<PreviewContent> {
+    let file_len = file.metadata().ok()?.len();
+    let moov = find_box(file, 0, file_len, b"moov")?;
+    let mvhd = find_box(file, moov.0, moov.1, b"mvhd")?;
+
+    file.seek(SeekFrom::Start(mvhd.0)).ok()?;
+    let mut version = [0u8; 4];
+    file.read_exact(&mut version).ok()?;
+
+    // Version 1 uses 64-bit creation/modification times and duration
+    let (timescale, duration) = if version[0] == 1 {
+        let mut buf = [0u8; 28];
+        file.read_exact(&mut buf).ok()?;
+        (
+            u32::from_be_bytes(buf[16..20].try_into().ok()?),
+            u64::from_be_bytes(buf[20..28].try_into().ok()?),
+        )
+    } else {
+        let mut buf = [0u8; 16];
+        file.read_exact(&mut buf).ok()?;
+        (
+            u32::from_be_bytes(buf[8..12].try_into().ok()?),
+            u32::from_be_bytes(buf[12..16].try_into().ok()?) as u64,
+        )
+    };
+
+    Some(PreviewContent::Media {
+        duration_secs: (timescale > 0).then(|| duration as f64 / timescale as f64),
+        sample_rate: None,
+        channels: None,
+    })
+}
+
+/// Search the boxes in `[start, end)` for `kind`, returning its payload range
+fn find_box(file: &mut File, start: u64, end: u64, kind: &[u8; 4]) -> Option<(u64, u64)> {
+    let mut offset = start;
+    while offset
+        .checked_add(8)
+        .is_some_and(|header_end| header_end <= end)
+    {
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header).ok()?;
+
+        let mut header_len = 8;
+        let mut box_len = u32::from_be_bytes(header[0..4].try_into().ok()?) as u64;
+        if box_len == 1 {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large).ok()?;
+            box_len = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if box_len == 0 {
+            box_len = end - offset;
+        }
+        if box_len < header_len {
+            return None;
+        }
+
+        // A corrupt 64-bit length can point past any file
+        let box_end = offset.checked_add(box_len)?;
+        if &header[4..8] == kind {
+            return Some((offset + header_len, box_end));
+        }
+        offset = box_end;
+    }
+    None
+}
+
+/// Read STREAMINFO, the metadata block every FLAC stream starts with
+fn read_flac_metadata(file: &mut File) -> Option<PreviewContent> {
+    file.seek(SeekFrom::Start(4)).ok()?;
+    let mut block = [0u8; 4 + 34];
+    file.read_exact(&mut block).ok()?;
+    if block[0] & 0x7F != 0 {
+        return None;
+    }
+
+    // 20 bits sample rate, 3 bits channels - 1, 5 bits bits per sample - 1,
+    // 36 bits total samples
+    let packed = u64::from_be_bytes(block[4 + 10..4 + 18].try_into().ok()?);
+    let sample_rate = (packed >> 44) as u32;
+    let total_samples = packed & 0xF_FFFF_FFFF;
+
+    Some(PreviewContent::Media {
+        duration_secs: (sample_rate > 0 && total_samples > 0)
+            .then(|| total_samples as f64 / sample_rate as f64),
+        sample_rate: (sample_rate > 0).then_some(sample_rate),
+        channels: Some(((packed >> 41) & 0x7) as u16 + 1),
+    })
+}
+
+/// Read the Vorbis or Opus identification header from the first page, and the
+/// duration from the granule position of the last page
+fn read_ogg_metadata(file: &mut File) -> Option<PreviewContent> {
+    let mut page = [0u8; 27];
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_exact(&mut page).ok()?;
+    file.seek(SeekFrom::Current(page[26] as i64)).ok()?;
+    let mut packet = [0u8; 16];
+    file.read_exact(&mut packet).ok()?;
+
+    // Opus granule positions count 48 kHz samples whatever the input rate was
+    let (sample_rate, channels, granule_rate, pre_skip) = if packet.starts_with(b"\x01vorbis") {
+        let rate = u32::from_le_bytes(packet[12..16].try_into().ok()?);
+        (rate, packet[11], rate, 0)
+    } else if packet.starts_with(b"OpusHead") {
+        let pre_skip = u16::from_le_bytes([packet[10], packet[11]]);
+        let rate = u32::from_le_bytes(packet[12..16].try_into().ok()?);
+        (rate, packet[9], 48000, pre_skip as u64)
+    } else {
+        return None;
+    };
+
+    let file_len = file.metadata().ok()?.len();
+    let tail_start = file_len.saturating_sub(OGG_TAIL_BYTES);
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(tail_start)).ok()?;
+    file.read_to_end(&mut tail).ok()?;
+    let granule = tail
+        .windows(4)
+        .rposition(|w| w == b"OggS")
+        .and_then(|at| tail.get(at + 6..at + 14))
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        // -1 marks pages on which no packet ends
+        .filter(|&granule| granule != u64::MAX);
+
+    Some(PreviewContent::Media {
+        duration_secs: granule
+            .filter(|_| granule_rate > 0)
+            .map(|granule| granule.saturating_sub(pre_skip) as f64 / granule_rate as f64),
+        sample_rate: (sample_rate > 0).then_some(sample_rate),
+        channels: Some(channels as u16),
+    })
+}
+
+/// Read the duration from `Segment/Info` and the first audio track's format
+/// from `Segment/Tracks`
+fn read_matroska_metadata(file: &mut File) -> Option<PreviewContent> {
+    let file_len = file.metadata().ok()?.len();
+    let segment = find_element(file, 0, file_len, MKV_SEGMENT)?;
+
+    let duration_secs = find_element(file, segment.0, segment.1, MKV_INFO).and_then(|info| {
+        // Durations are in ticks of TimecodeScale nanoseconds, 1 ms by default
+        let scale = find_element(file, info.0, info.1, MKV_TIMECODE_SCALE)
+            .and_then(|range| read_ebml_uint(file, range))
+            .unwrap_or(1_000_000);
+        let duration = find_element(file, info.0, info.1, MKV_DURATION)
+            .and_then(|range| read_ebml_float(file, range))?;
+        Some(duration * scale as f64 / 1e9)
+    });
+
+    let mut audio = None;
+    if let Some(tracks) = find_element(file, segment.0, segment.1, MKV_TRACKS) {
+        let mut offset = tracks.0;
+        while let Some(entry) = find_element(file, offset, tracks.1, MKV_TRACK_ENTRY) {
+            audio = find_element(file, entry.0, entry.1, MKV_AUDIO);
+            if audio.is_some() {
+                break;
+            }
+            offset = entry.1;
+        }
+    }
+
+    // Absent audio fields take their defaults from the Matroska spec
+    let (sample_rate, channels) = match audio {
+        Some(audio) => (
+            find_element(file, audio.0, audio.1, MKV_SAMPLING_FREQUENCY)
+                .and_then(|range| read_ebml_float(file, range))
+                .map_or(Some(8000), |rate| Some(rate.round() as u32)),
+            find_element(file, audio.0, audio.1, MKV_CHANNELS)
+                .and_then(|range| read_ebml_uint(file, range))
+                .map_or(Some(1), |channels| Some(channels as u16)),
+        ),
+        None => (None, None),
+    };
+
+    Some(PreviewContent::Media {
+        duration_secs,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Search the EBML elements in `[start, end)` for `id`, returning its data range.
+/// Elements of unknown size extend to `end` and can't be skipped.
+fn find_element(file: &mut File, start: u64, end: u64, id: u32) -> Option<(u64, u64)> {
+    let mut offset = start;
+    while offset < end {
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let (element_id, id_len) = read_vint(file)?;
+        let (raw_size, size_len) = read_vint(file)?;
+        let unknown = (1u64 << (7 * size_len)) - 1;
+        let data_start = offset + (id_len + size_len) as u64;
+        let data_end = match raw_size & unknown {
+            size if size == unknown => end,
+            size => data_start.checked_add(size)?.min(end),
+        };
+
+        if element_id == id as u64 {
+            return Some((data_start, data_end));
+        }
+        if data_end == end {
+            return None;
+        }
+        offset = data_end;
+    }
+    None
+}
+
+/// An EBML variable-length integer with its length marker kept, and its length
+fn read_vint(file: &mut File) -> Option<(u64, u32)> {
+    let mut first = [0u8; 1];
+    file.read_exact(&mut first).ok()?;
+    let len = first[0].leading_zeros() + 1;
+    if len > 8 {
+        return None;
+    }
+    let mut rest = [0u8; 7];
+    file.read_exact(&mut rest[..len as usize - 1]).ok()?;
+    let value = rest[..len as usize - 1]
+        .iter()
+        .fold(first[0] as u64, |value, &byte| value << 8 | byte as u64);
+    Some((value, len))
+}
+
+fn read_ebml_uint(file: &mut File, (start, end): (u64, u64)) -> Option<u64> {
+    let mut bytes = [0u8; 8];
+    let len = usize::try_from(end - start).ok().filter(|&len| len <= 8)?;
+    file.seek(SeekFrom::Start(start)).ok()?;
+    file.read_exact(&mut bytes[8 - len..]).ok()?;
+    Some(u64::from_be_bytes(bytes))
+}
+
+fn read_ebml_float(file: &mut File, (start, end): (u64, u64)) -> Option<f64> {
+    file.seek(SeekFrom::Start(start)).ok()?;
+    match end - start {
+        4 => {
+            let mut bytes = [0u8; 4];
+            file.read_exact(&mut bytes).ok()?;
+            Some(f32::from_be_bytes(bytes) as f64)
+        }
+        8 => {
+            let mut bytes = [0u8; 8];
+            file.read_exact(&mut bytes).ok()?;
+            Some(f64::from_be_bytes(bytes))
+        }
+        _ => None,
+    }
+}
+
+/// A Layer III frame header
+struct Mp3Frame {
+    mpeg1: bool,
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl Mp3Frame {
+    fn parse(header: &[u8]) -> Option<Self> {
+        let &[sync, flags, rates, mode, ..] = header else {
+            return None;
+        };
+        if sync != 0xFF || flags & 0xE0 != 0xE0 {
+            return None;
+        }
+        // Version 3 is MPEG-1, 2 is MPEG-2, 0 is MPEG-2.5 and 1 is reserved;
+        // layer 1 means Layer III
+        let version = (flags >> 3) & 0x3;
+        let layer = (flags >> 1) & 0x3;
+        let bitrate_index = (rates >> 4) as usize;
+        let rate_index = ((rates >> 2) & 0x3) as usize;
+        if version == 1 || layer != 1 || bitrate_index == 0 || bitrate_index == 15 {
+            return None;
+        }
+
+        let mpeg1 = version == 3;
+        let sample_rate = MP3_SAMPLE_RATES.get(rate_index)?
+            >> match version {
+                3 => 0,
+                2 => 1,
+                _ => 2,
+            };
+        Some(Self {
+            mpeg1,
+            bitrate_kbps: MP3_BITRATES[usize::from(!mpeg1)][bitrate_index],
+            sample_rate,
+            channels: if mode >> 6 == 3 { 1 } else { 2 },
+        })
+    }
+
+    /// Offset of a Xing/Info header from the start of the frame: after the
+    /// 4-byte header and the side information
+    fn xing_offset(&self) -> usize {
+        4 + match (self.mpeg1, self.channels) {
+            (true, 1) => 17,
+            (true, _) => 32,
+            (false, 1) => 9,
+            (false, _) => 17,
+        }
+    }
+
+    fn samples_per_frame(&self) -> u64 {
+        if self.mpeg1 {
+            1152
+        } else {
+            576
+        }
+    }
+}
+
+/// Find the first frame after any ID3v2 tag. The duration comes from the frame
+/// count of a Xing/Info header where present, otherwise from the bitrate.
+fn read_mp3_metadata(file: &mut File) -> Option<PreviewContent> {
+    let file_len = file.metadata().ok()?.len();
+    let mut tag = [0u8; 10];
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_exact(&mut tag).ok()?;
+    let audio_start = if &tag[0..3] == b"ID3" {
+        // Sizes are syncsafe: 7 bits per byte
+        let size = tag[6..10]
+            .iter()
+            .fold(0u64, |size, &byte| size << 7 | (byte & 0x7F) as u64);
+        let footer = if tag[5] & 0x10 != 0 { 10 } else { 0 };
+        10 + size + footer
+    } else {
+        0
+    };
+
+    let mut buffer = Vec::new();
+    file.seek(SeekFrom::Start(audio_start)).ok()?;
+    file.take(MP3_SYNC_SEARCH_BYTES)
+        .read_to_end(&mut buffer)
+        .ok()?;
+    let (position, frame) =
+        (0..buffer.len()).find_map(|i| Mp3Frame::parse(&buffer[i..]).map(|frame| (i, frame)))?;
+
+    let xing = position + frame.xing_offset();
+    let frames = buffer
+        .get(xing..xing + 12)
+        .filter(|xing| &xing[0..4] == b"Xing" || &xing[0..4] == b"Info")
+        // Bit 0 of the flags says the frame count is present
+        .filter(|xing| xing[7] & 0x1 != 0)
+        .map(|xing| u32::from_be_bytes(xing[8..12].try_into().unwrap()) as u64);
+    let duration_secs = match frames {
+        Some(frames) => frames as f64 * frame.samples_per_frame() as f64 / frame.sample_rate as f64,
+        None => {
+            let audio_bytes = file_len - audio_start - position as u64;
+            audio_bytes as f64 * 8.0 / (frame.bitrate_kbps as f64 * 1000.0)
+        }
+    };
+
+    Some(PreviewContent::Media {
+        duration_secs: Some(duration_secs),
+        sample_rate: Some(frame.sample_rate),
+        channels: Some(frame.channels),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write(dir: &TempDir, name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn preview(dir: &TempDir, name: &str, bytes: &[u8]) -> PreviewContent {
+        preview_file(&write(dir, name, bytes)).unwrap().content
+    }
+
+    fn text_encoding(content: PreviewContent) -> (String, String, bool) {
+        match content {
+            PreviewContent::Text {
+                encoding,
+                text,
+                truncated,
+            } => (encoding, text, truncated),
+            other => panic!("expected text, got {:?}", other),
+        }
+    }
+
+    fn media(content: PreviewContent) -> (Option<f64>, Option<u32>, Option<u16>) {
+        match content {
+            PreviewContent::Media {
+                duration_secs,
+                sample_rate,
+                channels,
+            } => (duration_secs, sample_rate, channels),
+            other => panic!("expected media, got {:?}", other),
+        }
+    }
+
+    fn archive(content: PreviewContent) -> (Vec<ArchiveEntry>, u64, bool, bool) {
+        match content {
+            PreviewContent::Archive {
+                entries,
+                total_entries,
+                total_is_lower_bound,
+                truncated,
+                ..
+            } => (entries, total_entries, total_is_lower_bound, truncated),
+            other => panic!("expected an archive, got {:?}", other),
+        }
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("duration");
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn utf8_text_is_decoded() {
+        let dir = TempDir::new().unwrap();
+        let (encoding, text, truncated) =
+            text_encoding(preview(&dir, "notes.txt", "héllo wörld".as_bytes()));
+        assert_eq!(encoding, "UTF-8");
+        assert_eq!(text, "héllo wörld");
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn bom_decides_the_encoding() {
+        let dir = TempDir::new().unwrap();
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("hi".encode_utf16().flat_map(u16::to_le_bytes));
+        let (encoding, text, _) = text_encoding(preview(&dir, "wide.txt", &bytes));
+        assert_eq!(encoding, "UTF-16LE");
+        assert_eq!(text, "hi");
+    }
+
+    #[test]
+    fn legacy_encodings_are_detected() {
+        let dir = TempDir::new().unwrap();
+        let source = "これは日本語のテキストファイルです。文字コードを判定します。";
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(source);
+        let (encoding, text, _) = text_encoding(preview(&dir, "sjis.txt", &bytes));
+        assert_eq!(encoding, "Shift_JIS");
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn nul_bytes_mean_binary() {
+        let dir = TempDir::new().unwrap();
+        assert!(matches!(
+            preview(&dir, "data.bin", b"abc\0def"),
+            PreviewContent::Binary
+        ));
+    }
+
+    #[test]
+    fn character_cut_by_the_read_limit_stays_utf8() {
+        let dir = TempDir::new().unwrap();
+        let mut bytes = vec![b'a'; TEXT_PREVIEW_BYTES - 1];
+        bytes.extend("éé".as_bytes());
+        let (encoding, text, truncated) = text_encoding(preview(&dir, "long.txt", &bytes));
+        assert_eq!(encoding, "UTF-8");
+        assert!(truncated);
+        assert!(text.starts_with("aaa"));
+    }
+
+    #[test]
+    fn images_report_dimensions_and_a_thumbnail() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("wide.png");
+        image::RgbImage::new(300, 150).save(&path).unwrap();
+        let preview = preview_file(&path).unwrap();
+        assert_eq!(preview.mime, "image/png");
+        match preview.content {
+            PreviewContent::Image {
+                width,
+                height,
+                thumbnail,
+            } => {
+                assert_eq!((width, height), (300, 150));
+                assert!(thumbnail.unwrap().starts_with("data:image/png;base64,"));
+            }
+            other => panic!("expected an image, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn zip_entries_are_listed() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.add_directory("docs/", options).unwrap();
+        zip.start_file("docs/readme.txt", options).unwrap();
+        zip.write_all(b"hello").unwrap();
+        zip.finish().unwrap();
+
+        let (entries, total, lower_bound, truncated) =
+            archive(preview_file(&path).unwrap().content);
+        assert_eq!(total, 2);
+        assert!(!lower_bound && !truncated);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[1].path, "docs/readme.txt");
+        assert_eq!(entries[1].size, 5);
+    }
+
+    fn tar_bytes(count: usize) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for i in 0..count {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(3);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("file{}", i), &b"abc"[..])
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn tar_entries_are_listed() {
+        let dir = TempDir::new().unwrap();
+        let (entries, total, lower_bound, truncated) =
+            archive(preview(&dir, "a.tar", &tar_bytes(3)));
+        assert_eq!(total, 3);
+        assert!(!lower_bound && !truncated);
+        assert_eq!(entries[2].path, "file2");
+        assert_eq!(entries[2].size, 3);
+    }
+
+    #[test]
+    fn long_tarballs_stop_at_the_entry_limit() {
+        let dir = TempDir::new().unwrap();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(&tar_bytes(MAX_ARCHIVE_ENTRIES + 50)).unwrap();
+        let (entries, total, lower_bound, truncated) =
+            archive(preview(&dir, "a.tar.gz", &gz.finish().unwrap()));
+        assert_eq!(entries.len(), MAX_ARCHIVE_ENTRIES);
+        assert_eq!(total, MAX_ARCHIVE_ENTRIES as u64 + 1);
+        assert!(lower_bound && truncated);
+    }
+
+    #[test]
+    fn wav_metadata() {
+        let dir = TempDir::new().unwrap();
+        // 1 s of 16-bit stereo at 8 kHz
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend(32000u32.to_le_bytes());
+        wav.extend(4u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(32000u32.to_le_bytes());
+        wav.resize(wav.len() + 32000, 0);
+        let (duration, rate, channels) = media(preview(&dir, "a.wav", &wav));
+        assert_close(duration, 1.0);
+        assert_eq!((rate, channels), (Some(8000), Some(2)));
+    }
+
+    /// MPEG-1 Layer III frame header, 128 kbit/s at 44.1 kHz in stereo or mono
+    fn mp3_header(mono: bool) -> [u8; 4] {
+        [0xFF, 0xFB, 0x90, if mono { 0xC0 } else { 0x00 }]
+    }
+
+    #[test]
+    fn mp3_duration_from_the_bitrate() {
+        let dir = TempDir::new().unwrap();
+        // An ID3 tag of 20 bytes, then 2 s of 128 kbit/s audio
+        let mut mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x14".to_vec();
+        mp3.resize(mp3.len() + 20, 0);
+        let audio_start = mp3.len();
+        mp3.extend(mp3_header(false));
+        mp3.resize(audio_start + 2 * 128_000 / 8, 0);
+        let (duration, rate, channels) = media(preview(&dir, "a.mp3", &mp3));
+        assert_close(duration, 2.0);
+        assert_eq!((rate, channels), (Some(44100), Some(2)));
+    }
+
+    #[test]
+    fn mp3_duration_from_a_xing_header() {
+        let dir = TempDir::new().unwrap();
+        let mut mp3 = mp3_header(true).to_vec();
+        mp3.resize(4 + 17, 0);
+        mp3.extend(b"Xing");
+        mp3.extend(1u32.to_be_bytes());
+        // 115 frames of 1152 samples, about 3 s at 44.1 kHz
+        mp3.extend(115u32.to_be_bytes());
+        mp3.resize(4096, 0);
+        let (duration, rate, channels) = media(preview(&dir, "a.mp3", &mp3));
+        assert_close(duration, 115.0 * 1152.0 / 44100.0);
+        assert_eq!((rate, channels), (Some(44100), Some(1)));
+    }
+
+    #[test]
+    fn mp3_frames_are_only_searched_for_in_mp3_files() {
+        let dir = TempDir::new().unwrap();
+        let mut frames = mp3_header(false).to_vec();
+        frames.resize(4096, 0);
+        let (duration, rate, channels) = media(preview(&dir, "a.avi", &frames));
+        assert_eq!((duration, rate, channels), (None, None, None));
+    }
+
+    #[test]
+    fn mp4_box_lengths_past_the_end_are_ignored() {
+        let dir = TempDir::new().unwrap();
+        let mut mp4 = 16u32.to_be_bytes().to_vec();
+        mp4.extend(b"ftypisom");
+        mp4.extend([0; 4]);
+        // A 64-bit length that overflows the offset of the next box
+        mp4.extend(1u32.to_be_bytes());
+        mp4.extend(b"free");
+        mp4.extend((u64::MAX - 8).to_be_bytes());
+        let (duration, rate, channels) = media(preview(&dir, "a.mp4", &mp4));
+        assert_eq!((duration, rate, channels), (None, None, None));
+    }
+
+    #[test]
+    fn flac_metadata() {
+        let dir = TempDir::new().unwrap();
+        let mut flac = b"fLaC".to_vec();
+        // Last metadata block, STREAMINFO, 34 bytes
+        flac.extend([0x80, 0, 0, 34]);
+        flac.extend([0u8; 10]);
+        let packed: u64 = (48000 << 44) | (1 << 41) | (15 << 36) | (48000 * 5);
+        flac.extend(packed.to_be_bytes());
+        flac.extend([0u8; 16]);
+        let (duration, rate, channels) = media(preview(&dir, "a.flac", &flac));
+        assert_close(duration, 5.0);
+        assert_eq!((rate, channels), (Some(48000), Some(2)));
+    }
+
+    fn ogg_page(granule: u64, packet: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend(granule.to_le_bytes());
+        page.extend([0u8; 12]);
+        page.push(1);
+        page.push(packet.len() as u8);
+        page.extend(packet);
+        page
+    }
+
+    #[test]
+    fn ogg_vorbis_metadata() {
+        let dir = TempDir::new().unwrap();
+        let mut identification = b"\x01vorbis".to_vec();
+        identification.extend(0u32.to_le_bytes());
+        identification.push(2);
+        identification.extend(44100u32.to_le_bytes());
+        identification.extend([0u8; 14]);
+        let mut ogg = ogg_page(0, &identification);
+        ogg.extend(ogg_page(44100 * 7, &[0u8; 20]));
+        let (duration, rate, channels) = media(preview(&dir, "a.ogg", &ogg));
+        assert_close(duration, 7.0);
+        assert_eq!((rate, channels), (Some(44100), Some(2)));
+    }
+
+    #[test]
+    fn ogg_opus_durations_count_48_khz_samples() {
+        let dir = TempDir::new().unwrap();
+        let mut head = b"OpusHead\x01\x01".to_vec();
+        head.extend(312u16.to_le_bytes());
+        head.extend(16000u32.to_le_bytes());
+        head.extend([0u8; 3]);
+        let mut ogg = ogg_page(0, &head);
+        ogg.extend(ogg_page(48000 * 2 + 312, &[0u8; 20]));
+        let (duration, rate, channels) = media(preview(&dir, "a.ogg", &ogg));
+        assert_close(duration, 2.0);
+        assert_eq!((rate, channels), (Some(16000), Some(1)));
+    }
+
+    /// An EBML element with a one-byte size
+    fn element(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.push(0x80 | data.len() as u8);
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn matroska_metadata() {
+        let dir = TempDir::new().unwrap();
+        let info = [
+            element(&[0x2A, 0xD7, 0xB1], &1_000_000u32.to_be_bytes()),
+            element(&[0x44, 0x89], &90_500f64.to_be_bytes()),
+        ]
+        .concat();
+        let video = element(&[0xAE], &element(&[0x83], &[1]));
+        let audio = element(
+            &[0xE1],
+            &[
+                element(&[0xB5], &48000f32.to_be_bytes()),
+                element(&[0x9F], &[6]),
+            ]
+            .concat(),
+        );
+        let tracks = [video, element(&[0xAE], &audio)].concat();
+        let segment = [
+            element(&[0x15, 0x49, 0xA9, 0x66], &info),
+            element(&[0x16, 0x54, 0xAE, 0x6B], &tracks),
+        ]
+        .concat();
+
+        let mut mkv = element(&EBML_MAGIC, &element(&[0x42, 0x82], b"matroska"));
+        // Segment with an unknown size, as written by live muxers
+        mkv.extend([
+            0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        ]);
+        mkv.extend(segment);
+        let (duration, rate, channels) = media(preview(&dir, "a.mkv", &mkv));
+        assert_close(duration, 90.5);
+        assert_eq!((rate, channels), (Some(48000), Some(6)));
+    }
+
+    #[test]
+    fn unreadable_media_has_empty_metadata() {
+        let dir = TempDir::new().unwrap();
+        let (duration, rate, channels) = media(preview(&dir, "a.mp3", &[0u8; 64]));
+        assert_eq!((duration, rate, channels), (None, None, None));
+    }
+}
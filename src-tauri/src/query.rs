@@ -0,0 +1,813 @@
+//! Query language for searching a scan tree.
+//!
+//! A query is a list of terms that must all match. Terms can be combined with
+//! `OR`, negated with `-` or `NOT`, and grouped with parentheses:
+//!
+//! ```text
+//! size>1GB ext:iso modified<2024-01-01 under:~/Downloads
+//! (ext:mp4 OR ext:mkv) -name:*sample* age>2y owner:alice
+//! ```
+//!
+//! | Field      | Operators             | Value                                  |
+//! |------------|-----------------------|----------------------------------------|
+//! | `size`     | `< <= = >= >`         | bytes or `500MB`, `1.5GB`              |
+//! | `name`     | `:` glob, `~` regex   | case-insensitive                       |
+//! | `ext`      | `:`                   | comma-separated extensions             |
+//! | `modified` | `< <= = >= >`         | `YYYY-MM-DD`                           |
+//! | `age`      | `< <= >= >`           | `12h`, `30d`, `2w`, `6m`, `2y`         |
+//! | `owner`    | `:`                   | user name or uid                       |
+//! | `group`    | `:`                   | group name or gid                      |
+//! | `depth`    | `< <= = >= >`         | levels below the search root           |
+//! | `type`     | `:`                   | `file` or `dir`                        |
+//! | `under`    | `:`                   | path prefix, `~` expands to home       |
+//!
+//! A bare word matches names containing it, or names matching it as a glob if
+//! it contains `*` or `?`. Values with spaces or parentheses can be quoted.
+
+use crate::scanner::{DirNode, SortKey};
+use crate::util::{group_name, parse_size, user_name};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Results per page when no limit is given
+pub const DEFAULT_SEARCH_LIMIT: usize = 100;
+/// Upper bound on results per page
+pub const MAX_SEARCH_LIMIT: usize = 1000;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Cmp {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            "<" => Some(Cmp::Lt),
+            "<=" => Some(Cmp::Le),
+            "=" | ":" => Some(Cmp::Eq),
+            ">=" => Some(Cmp::Ge),
+            ">" => Some(Cmp::Gt),
+            _ => None,
+        }
+    }
+
+    /// The comparison seen from the other side, e.g. `age > x` is `mtime < now - x`
+    fn flipped(self) -> Self {
+        match self {
+            Cmp::Lt => Cmp::Gt,
+            Cmp::Le => Cmp::Ge,
+            Cmp::Eq => Cmp::Eq,
+            Cmp::Ge => Cmp::Le,
+            Cmp::Gt => Cmp::Lt,
+        }
+    }
+
+    fn test<T: Ord>(self, value: T, target: T) -> bool {
+        let ordering = value.cmp(&target);
+        match self {
+            Cmp::Lt => ordering == Ordering::Less,
+            Cmp::Le => ordering != Ordering::Greater,
+            Cmp::Eq => ordering == Ordering::Equal,
+            Cmp::Ge => ordering != Ordering::Less,
+            Cmp::Gt => ordering == Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum OwnerMatch {
+    Id(u32),
+    Name(String),
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Size(Cmp, u64),
+    NameGlob(GlobMatcher),
+    NameRegex(Regex),
+    /// Lowercased substring of the name
+    NameContains(String),
+    /// Lowercased extensions without the dot
+    Ext(Vec<String>),
+    /// Compared against the node's newest modification time
+    Modified(Cmp, u64),
+    Owner(OwnerMatch),
+    Group(OwnerMatch),
+    Depth(Cmp, usize),
+    IsFile(bool),
+    Under(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+/// A parsed query, ready to evaluate against scan nodes
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr,
+}
+
+impl Query {
+    /// Parse `input`; relative ages are resolved against `now` (seconds since the epoch)
+    pub fn parse(input: &str, now: u64) -> Result<Self, String> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            now,
+        };
+        if parser.tokens.is_empty() {
+            return Err("Empty query".to_string());
+        }
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("Unexpected '{}'", token.text()));
+        }
+        Ok(Self { expr })
+    }
+
+    fn matches(&self, node: &DirNode, depth: usize, names: &mut NameCache) -> bool {
+        self.expr.matches(node, depth, names)
+    }
+}
+
+impl Expr {
+    fn matches(&self, node: &DirNode, depth: usize, names: &mut NameCache) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|e| e.matches(node, depth, names)),
+            Expr::Or(exprs) => exprs.iter().any(|e| e.matches(node, depth, names)),
+            Expr::Not(expr) => !expr.matches(node, depth, names),
+            Expr::Term(term) => term.matches(node, depth, names),
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, node: &DirNode, depth: usize, names: &mut NameCache) -> bool {
+        match self {
+            Term::Size(cmp, size) => cmp.test(node.size, *size),
+            Term::NameGlob(glob) => glob.is_match(&node.name),
+            Term::NameRegex(regex) => regex.is_match(&node.name),
+            Term::NameContains(text) => node.name.to_lowercase().contains(text),
+            Term::Ext(extensions) => {
+                node.is_file && {
+                    let ext = Path::new(&node.name)
+                        .extension()
+                        .map(|e| e.to_string_lossy().to_lowercase());
+                    ext.is_some_and(|ext| extensions.contains(&ext))
+                }
+            }
+            Term::Modified(cmp, time) => node.mtime.is_some_and(|mtime| cmp.test(mtime, *time)),
+            Term::Owner(owner) => node
+                .uid
+                .is_some_and(|uid| owner_matches(owner, uid, &mut names.users, user_name)),
+            Term::Group(group) => node
+                .gid
+                .is_some_and(|gid| owner_matches(group, gid, &mut names.groups, group_name)),
+            Term::Depth(cmp, target) => cmp.test(depth, *target),
+            Term::IsFile(is_file) => node.is_file == *is_file,
+            Term::Under(prefix) => Path::new(&node.path).starts_with(prefix),
+        }
+    }
+}
+
+/// Resolved user and group names, so each id hits the system database once per search
+#[derive(Default)]
+struct NameCache {
+    users: HashMap<u32, Option<String>>,
+    groups: HashMap<u32, Option<String>>,
+}
+
+fn owner_matches(
+    owner: &OwnerMatch,
+    id: u32,
+    cache: &mut HashMap<u32, Option<String>>,
+    resolve: fn(u32) -> Option<String>,
+) -> bool {
+    match owner {
+        OwnerMatch::Id(expected) => id == *expected,
+        OwnerMatch::Name(expected) => cache
+            .entry(id)
+            .or_insert_with(|| resolve(id))
+            .as_ref()
+            .is_some_and(|name| name == expected),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        match self {
+            Token::Open => "(",
+            Token::Close => ")",
+            Token::Word(word) => word,
+        }
+    }
+}
+
+/// Split on whitespace and parentheses; double quotes group characters and are removed
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            _ if quoted => word.push(c),
+            '(' | ')' => {
+                if in_word {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                    in_word = false;
+                }
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            _ if c.is_whitespace() => {
+                if in_word {
+                    tokens.push(Token::Word(std::mem::take(&mut word)));
+                    in_word = false;
+                }
+            }
+            _ => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if quoted {
+        return Err("Unterminated quote".to_string());
+    }
+    if in_word {
+        tokens.push(Token::Word(word));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    now: u64,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w == keyword)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut branches = vec![self.parse_and()?];
+        while self.peek_keyword("OR") {
+            self.pos += 1;
+            branches.push(self.parse_and()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Expr::Or(branches)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut terms = Vec::new();
+        while let Some(token) = self.peek() {
+            if *token == Token::Close || self.peek_keyword("OR") {
+                break;
+            }
+            if self.peek_keyword("AND") {
+                self.pos += 1;
+                continue;
+            }
+            terms.push(self.parse_unary()?);
+        }
+        match terms.len() {
+            0 => Err("Expected a search term".to_string()),
+            1 => Ok(terms.remove(0)),
+            _ => Ok(Expr::And(terms)),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or("Expected a search term")?;
+        self.pos += 1;
+
+        match token {
+            Token::Open => {
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err("Missing ')'".to_string());
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Token::Close => Err("Unexpected ')'".to_string()),
+            Token::Word(word) if word == "NOT" || word == "-" => {
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Token::Word(word) => match word.strip_prefix('-') {
+                Some(rest) => Ok(Expr::Not(Box::new(Expr::Term(parse_term(rest, self.now)?)))),
+                None => Ok(Expr::Term(parse_term(&word, self.now)?)),
+            },
+        }
+    }
+}
+
+fn parse_term(word: &str, now: u64) -> Result<Term, String> {
+    let key_len = word
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(word.len());
+    let (key, rest) = word.split_at(key_len);
+    let op_len = rest
+        .find(|c: char| !matches!(c, '<' | '>' | '=' | ':' | '~'))
+        .unwrap_or(rest.len());
+    let (op, value) = rest.split_at(op_len);
+
+    if key.is_empty() || op.is_empty() {
+        return bare_word(word);
+    }
+    if value.is_empty() {
+        return Err(format!("Missing value for '{}'", key));
+    }
+
+    let invalid_op = || format!("Operator '{}' isn't supported for '{}'", op, key);
+    let cmp = || Cmp::parse(op).ok_or_else(invalid_op);
+    let equality = || {
+        if op == ":" || op == "=" {
+            Ok(())
+        } else {
+            Err(invalid_op())
+        }
+    };
+
+    match key.to_ascii_lowercase().as_str() {
+        "size" => Ok(Term::Size(cmp()?, parse_size(value)?)),
+        "name" if op == "~" => RegexBuilder::new(value)
+            .case_insensitive(true)
+            .build()
+            .map(Term::NameRegex)
+            .map_err(|e| format!("Invalid regex: {}", e)),
+        "name" => {
+            equality()?;
+            glob(value).map(Term::NameGlob)
+        }
+        "ext" => {
+            equality()?;
+            Ok(Term::Ext(
+                value
+                    .split(',')
+                    .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                    .collect(),
+            ))
+        }
+        "modified" | "mtime" => Ok(Term::Modified(cmp()?, parse_date(value)?)),
+        "age" => {
+            let age = parse_duration(value)?;
+            Ok(Term::Modified(cmp()?.flipped(), now.saturating_sub(age)))
+        }
+        "owner" | "user" => {
+            equality()?;
+            Ok(Term::Owner(owner_match(value)))
+        }
+        "group" => {
+            equality()?;
+            Ok(Term::Group(owner_match(value)))
+        }
+        "depth" => value
+            .parse()
+            .map_err(|_| format!("Invalid depth '{}'", value))
+            .and_then(|depth| Ok(Term::Depth(cmp()?, depth))),
+        "type" | "is" => {
+            equality()?;
+            match value.to_ascii_lowercase().as_str() {
+                "file" => Ok(Term::IsFile(true)),
+                "dir" | "directory" | "folder" => Ok(Term::IsFile(false)),
+                _ => Err(format!("Unknown type '{}', expected file or dir", value)),
+            }
+        }
+        "under" | "in" => {
+            equality()?;
+            Ok(Term::Under(expand_home(value)))
+        }
+        _ => Err(format!("Unknown field '{}'", key)),
+    }
+}
+
+fn bare_word(word: &str) -> Result<Term, String> {
+    if word.is_empty() {
+        Err("Empty search term".to_string())
+    } else if word.contains(['*', '?']) {
+        glob(word).map(Term::NameGlob)
+    } else {
+        Ok(Term::NameContains(word.to_lowercase()))
+    }
+}
+
+fn glob(pattern: &str) -> Result<GlobMatcher, String> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| format!("Invalid glob: {}", e))
+}
+
+fn owner_match(value: &str) -> OwnerMatch {
+    value
+        .parse()
+        .map(OwnerMatch::Id)
+        .unwrap_or_else(|_| OwnerMatch::Name(value.to_string()))
+}
+
+fn expand_home(value: &str) -> PathBuf {
+    match (value.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(value),
+    }
+}
+
+/// `12h`, `30d`, `2w`, `6m` (30 days) or `2y` (365 days), in seconds
+fn parse_duration(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid age '{}'", value);
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit.to_ascii_lowercase().as_str() {
+        "h" => 60 * 60,
+        "d" => SECS_PER_DAY,
+        "w" => 7 * SECS_PER_DAY,
+        "m" => 30 * SECS_PER_DAY,
+        "y" => 365 * SECS_PER_DAY,
+        _ => return Err(invalid()),
+    };
+    Ok(number.saturating_mul(unit_secs))
+}
+
+/// `YYYY-MM-DD` (UTC midnight) in seconds since the epoch
+fn parse_date(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid date '{}', expected YYYY-MM-DD", value);
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let month: i64 = month.parse().map_err(|_| invalid())?;
+    let day: i64 = day.parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    // Days from civil date, after Howard Hinnant's algorithm
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    u64::try_from(days * SECS_PER_DAY as i64).map_err(|_| invalid())
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SearchOptions {
+    /// Directory to search within; defaults to the scan root
+    #[serde(default)]
+    pub root: Option<String>,
+    #[serde(default)]
+    pub sort: SortKey,
+    /// Defaults to descending for size, date and count, ascending for name and path
+    #[serde(default)]
+    pub descending: Option<bool>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    /// Number of matches before paging
+    pub total: usize,
+    pub offset: usize,
+    /// Matching nodes without their children
+    pub items: Vec<DirNode>,
+}
+
+/// Evaluate `query` against every node below the search root
+pub fn search(
+    tree: &DirNode,
+    query: &Query,
+    options: &SearchOptions,
+) -> Result<SearchResults, String> {
+    let root = match &options.root {
+        Some(path) => tree
+            .find(&expand_home(path))
+            .ok_or("Path is not part of the last scan")?,
+        None => tree,
+    };
+
+    let mut matches = Vec::new();
+    let mut names = NameCache::default();
+    collect_matches(root, 0, query, &mut names, &mut matches);
+
+    let descending = options
+        .descending
+        .unwrap_or(options.sort.descending_by_default());
+    matches.sort_by(|a, b| {
+        let ordering = options.sort.compare(a, b);
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    let limit = options
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .min(MAX_SEARCH_LIMIT);
+    let items = matches
+        .iter()
+        .skip(options.offset)
+        .take(limit)
+        .map(|node| {
+            let mut item = node.clone_shallow();
+            item.has_children = !node.children.is_empty();
+            item
+        })
+        .collect();
+
+    Ok(SearchResults {
+        total: matches.len(),
+        offset: options.offset,
+        items,
+    })
+}
+
+fn collect_matches<'a>(
+    node: &'a DirNode,
+    depth: usize,
+    query: &Query,
+    names: &mut NameCache,
+    matches: &mut Vec<&'a DirNode>,
+) {
+    for child in node.children.iter().filter(|c| !c.is_summary()) {
+        if query.matches(child, depth + 1, names) {
+            matches.push(child);
+        }
+        collect_matches(child, depth + 1, query, names, matches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1000 * SECS_PER_DAY;
+
+    fn file(path: &str, size: u64, days_old: u64) -> DirNode {
+        serde_json::from_value(serde_json::json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "size": size,
+            "item_count": 1,
+            "is_file": true,
+            "mtime": NOW - days_old * SECS_PER_DAY,
+        }))
+        .unwrap()
+    }
+
+    fn dir(path: &str, children: Vec<DirNode>) -> DirNode {
+        let mut node: DirNode = serde_json::from_value(serde_json::json!({
+            "name": path.rsplit('/').next().unwrap(),
+            "path": path,
+            "size": children.iter().map(|c| c.size).sum::<u64>(),
+            "item_count": children.iter().map(|c| c.item_count).sum::<u64>(),
+            "is_file": false,
+            "mtime": children.iter().filter_map(|c| c.mtime).max(),
+        }))
+        .unwrap();
+        node.children = children;
+        node
+    }
+
+    fn tree() -> DirNode {
+        dir(
+            "/r",
+            vec![
+                dir(
+                    "/r/docs",
+                    vec![
+                        file("/r/docs/report.pdf", 2_000, 30),
+                        file("/r/docs/my notes.txt", 10, 1),
+                    ],
+                ),
+                dir(
+                    "/r/video",
+                    vec![
+                        file("/r/video/a.MP4", 5_000_000, 400),
+                        file("/r/video/b.mkv", 3_000_000, 10),
+                    ],
+                ),
+                file("/r/old.iso", 2 << 30, 999),
+            ],
+        )
+    }
+
+    /// Names matching `input`, in the given sort order
+    fn find_sorted(input: &str, sort: SortKey) -> Vec<String> {
+        let query = Query::parse(input, NOW).unwrap();
+        let options = SearchOptions {
+            sort,
+            descending: Some(false),
+            ..Default::default()
+        };
+        search(&tree(), &query, &options)
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|node| node.name)
+            .collect()
+    }
+
+    fn find(input: &str) -> Vec<String> {
+        find_sorted(input, SortKey::Name)
+    }
+
+    fn parse_error(input: &str) -> String {
+        Query::parse(input, NOW).unwrap_err()
+    }
+
+    #[test]
+    fn empty_queries_are_rejected() {
+        assert_eq!(parse_error(""), "Empty query");
+        assert_eq!(parse_error("   "), "Empty query");
+        assert_eq!(parse_error(r#""""#), "Empty search term");
+        assert_eq!(parse_error("()"), "Expected a search term");
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            find("ext:pdf size>1KB OR ext:iso"),
+            ["old.iso", "report.pdf"]
+        );
+        assert_eq!(find("ext:pdf AND size>1MB OR ext:iso"), ["old.iso"]);
+        assert_eq!(
+            find("type:file (ext:pdf OR ext:txt)"),
+            ["my notes.txt", "report.pdf"]
+        );
+    }
+
+    #[test]
+    fn negation_applies_to_one_term_or_group() {
+        assert_eq!(
+            find("type:file -ext:mkv -ext:mp4"),
+            ["my notes.txt", "old.iso", "report.pdf"]
+        );
+        assert_eq!(
+            find("type:file NOT (ext:mkv OR ext:mp4 OR ext:iso)"),
+            ["my notes.txt", "report.pdf"]
+        );
+    }
+
+    #[test]
+    fn sizes_accept_binary_units() {
+        assert_eq!(find("size>=2GB"), ["old.iso"]);
+        assert_eq!(find("type:file size<1.5K"), ["my notes.txt"]);
+        assert_eq!(find("type:dir size>7MB size<8MB"), ["video"]);
+        assert_eq!(parse_error("size>5XB"), "Invalid size unit: XB");
+        assert_eq!(parse_error("size>big"), "Invalid size: big");
+    }
+
+    #[test]
+    fn ages_and_dates_compare_against_the_newest_mtime() {
+        assert_eq!(find("type:file age<2w"), ["b.mkv", "my notes.txt"]);
+        assert_eq!(find("type:file age>1y"), ["a.MP4", "old.iso"]);
+        assert_eq!(find("type:dir age>6m"), Vec::<String>::new());
+        assert_eq!(find("type:file age<=24h"), ["my notes.txt"]);
+        assert_eq!(parse_duration("3w"), Ok(21 * SECS_PER_DAY));
+        assert_eq!(parse_error("age>3x"), "Invalid age '3x'");
+
+        assert_eq!(parse_date("1970-01-02"), Ok(SECS_PER_DAY));
+        assert_eq!(parse_date("2000-03-01"), Ok(951_868_800));
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("2024-01").is_err());
+    }
+
+    #[test]
+    fn quotes_group_words_with_spaces_and_parentheses() {
+        assert_eq!(find(r#"name:"my notes*""#), ["my notes.txt"]);
+        assert_eq!(find(r#""notes.txt""#), ["my notes.txt"]);
+        assert_eq!(find(r#"name:"(*""#), Vec::<String>::new());
+        assert_eq!(parse_error(r#"name:"my notes"#), "Unterminated quote");
+    }
+
+    #[test]
+    fn names_match_by_substring_glob_or_regex_ignoring_case() {
+        assert_eq!(find("REPORT"), ["report.pdf"]);
+        assert_eq!(find("*.mp4"), ["a.MP4"]);
+        assert_eq!(find("name:?.mkv"), ["b.mkv"]);
+        assert_eq!(find(r#"name~"^[ab]\.(mp4|mkv)$""#), ["a.MP4", "b.mkv"]);
+    }
+
+    #[test]
+    fn invalid_patterns_and_fields_are_reported() {
+        assert!(parse_error(r#"name~"(""#).starts_with("Invalid regex"));
+        assert!(parse_error("name:[").starts_with("Invalid glob"));
+        assert_eq!(parse_error("colour:red"), "Unknown field 'colour'");
+        assert_eq!(
+            parse_error("ext>pdf"),
+            "Operator '>' isn't supported for 'ext'"
+        );
+        assert_eq!(parse_error("size>"), "Missing value for 'size'");
+        assert_eq!(parse_error("(ext:pdf"), "Missing ')'");
+        assert_eq!(parse_error("ext:pdf )"), "Unexpected ')'");
+    }
+
+    #[test]
+    fn depth_type_and_under_filter_by_position() {
+        assert_eq!(find("depth=1"), ["docs", "old.iso", "video"]);
+        assert_eq!(find("depth>1 under:/r/video"), ["a.MP4", "b.mkv"]);
+        assert_eq!(find("type:dir"), ["docs", "video"]);
+    }
+
+    #[test]
+    fn results_are_sorted_then_paged() {
+        let query = Query::parse("type:file", NOW).unwrap();
+        let page = |offset, limit| {
+            let options = SearchOptions {
+                offset,
+                limit: Some(limit),
+                ..Default::default()
+            };
+            let results = search(&tree(), &query, &options).unwrap();
+            assert_eq!(results.total, 5);
+            results
+                .items
+                .into_iter()
+                .map(|n| n.name)
+                .collect::<Vec<_>>()
+        };
+
+        // Size sorts largest first by default
+        assert_eq!(page(0, 2), ["old.iso", "a.MP4"]);
+        assert_eq!(page(2, 2), ["b.mkv", "report.pdf"]);
+        assert_eq!(page(4, 2), ["my notes.txt"]);
+        assert!(page(10, 2).is_empty());
+
+        assert_eq!(
+            find_sorted("type:file", SortKey::Modified),
+            ["old.iso", "a.MP4", "report.pdf", "b.mkv", "my notes.txt"]
+        );
+    }
+
+    #[test]
+    fn search_root_must_be_in_the_tree() {
+        let query = Query::parse("type:file", NOW).unwrap();
+        let options = SearchOptions {
+            root: Some("/r/docs".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search(&tree(), &query, &options).unwrap().total, 2);
+
+        let options = SearchOptions {
+            root: Some("/elsewhere".to_string()),
+            ..Default::default()
+        };
+        assert!(search(&tree(), &query, &options).is_err());
+    }
+}
@@ -1,10 +1,18 @@
+use crate::checkpoint::{
+    self, Checkpoint, CheckpointRecord, CheckpointSettings, CheckpointWriter, LinkedFile,
+};
+use crate::file_identity::{FileIdentity, IdentitySource, PlatformIdentities};
+use crate::owners::OwnerTotals;
+use crate::throttle::{self, Throttle};
 use dashmap::{DashMap, DashSet};
 use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirNode {
@@ -18,11 +26,195 @@ pub struct DirNode {
     /// True if this directory has children that weren't serialized (lazy loading)
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub has_children: bool,
+    /// Newest modification time in the subtree, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+    /// Newest access time in the subtree; absent where atime isn't tracked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atime: Option<u64>,
+    /// Bytes in files not modified since the scanner's stale cutoff
+    #[serde(default)]
+    pub stale_size: u64,
+    /// Owning user and group ids (Unix only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Per-owner totals of the files in the subtree (directories only). Kept in
+    /// the stored tree but left out of copies made for responses and checkpoints.
+    #[serde(skip)]
+    pub owners: OwnerTotals,
+    /// Directory whose scan was cancelled or hit a limit before all of its entries were read
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub incomplete: bool,
+    /// Whether this is a real entry or a synthetic node (responses only)
+    #[serde(default, skip_serializing_if = "NodeKind::is_entry")]
+    pub kind: NodeKind,
+    /// Number of entries merged into this synthetic summary node (responses only)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub aggregated_count: u64,
+    /// Where this entry points, if it is a symlink
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// A file or directory on disk
+    #[default]
+    Entry,
+    /// Stands in for merged siblings; its path exists on no filesystem
+    Summary,
+}
+
+impl NodeKind {
+    fn is_entry(&self) -> bool {
+        *self == NodeKind::Entry
+    }
+}
+
+/// How symlinks below the scan root are handled; the root itself is always followed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Count the link itself (usually no disk usage) without following it
+    #[default]
+    Link,
+    /// Count and traverse the target; a directory or file reached more than once,
+    /// directly or through links, is only counted the first time
+    Follow,
+    /// Leave links out of the tree
+    Skip,
+}
+
+fn is_symlink(entry: &fs::DirEntry) -> bool {
+    entry.file_type().is_ok_and(|t| t.is_symlink())
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Merges each directory's smallest children into one "N smaller items" node in
+/// responses. The stored scan tree is never aggregated.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Aggregation {
+    /// Children smaller than this many bytes are merged
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    /// Children beyond the largest `top_k` are merged
+    #[serde(default)]
+    pub top_k: Option<usize>,
+}
+
+impl Aggregation {
+    fn is_active(&self) -> bool {
+        self.min_size.is_some() || self.top_k.is_some()
+    }
+
+    /// Children to keep, largest first, and a summary node for the rest. Nothing is
+    /// merged unless at least two children qualify.
+    fn split<'a>(
+        &self,
+        parent_path: &str,
+        children: &'a [DirNode],
+    ) -> (Vec<&'a DirNode>, Option<DirNode>) {
+        let mut kept: Vec<&DirNode> = children.iter().collect();
+        if !self.is_active() {
+            return (kept, None);
+        }
+
+        // Equal sizes are broken by name so the same children are merged every time
+        kept.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        let cut = kept
+            .iter()
+            .enumerate()
+            .position(|(i, child)| {
+                self.top_k.is_some_and(|k| i >= k)
+                    || self.min_size.is_some_and(|min| child.size < min)
+            })
+            .unwrap_or(kept.len());
+        if kept.len() - cut < 2 {
+            return (kept, None);
+        }
+
+        let merged = kept.split_off(cut);
+        (kept, Some(DirNode::summary(parent_path, &merged)))
+    }
 }
 
 /// Depth limit for lazy loading responses
 pub const LAZY_LOAD_DEPTH: usize = 2;
 
+/// Directories up to this many levels below the root are streamed as they finish
+pub const STREAM_DEPTH: usize = 2;
+
+/// Files untouched for this long count towards `stale_size` unless configured otherwise
+pub const DEFAULT_STALE_DAYS: u64 = 365;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Current time in seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn to_secs(time: std::io::Result<SystemTime>) -> Option<u64> {
+    time.ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+/// Modification and access times of an entry. Access times that predate the
+/// modification time are dropped: that is what noatime mounts leave behind.
+fn timestamps(metadata: &fs::Metadata) -> (Option<u64>, Option<u64>) {
+    let mtime = to_secs(metadata.modified());
+    let atime = to_secs(metadata.accessed()).filter(|&atime| mtime.is_none_or(|m| atime >= m));
+    (mtime, atime)
+}
+
+#[cfg(unix)]
+fn ownership(metadata: &fs::Metadata) -> (Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+    (Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn ownership(_metadata: &fs::Metadata) -> (Option<u32>, Option<u32>) {
+    (None, None)
+}
+
+/// The device an entry lives on, from its metadata on Unix or its listed
+/// identity on Windows
+#[cfg(unix)]
+fn device_of(metadata: &fs::Metadata, _known: Option<FileIdentity>) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_metadata: &fs::Metadata, known: Option<FileIdentity>) -> Option<u64> {
+    known.map(|identity| identity.volume)
+}
+
+/// Space a file takes on disk, which is less than its length for sparse files
+#[cfg(unix)]
+pub(crate) fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // blocks() returns 512-byte blocks, not filesystem blocks
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+pub(crate) fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
 impl DirNode {
     /// Create a new file node
     fn file(name: String, path: String, size: u64) -> Self {
@@ -34,19 +226,47 @@ impl DirNode {
             children: vec![],
             is_file: true,
             has_children: false,
+            incomplete: false,
+            kind: NodeKind::Entry,
+            aggregated_count: 0,
+            symlink_target: None,
+            mtime: None,
+            atime: None,
+            stale_size: 0,
+            uid: None,
+            gid: None,
+            owners: OwnerTotals::default(),
         }
     }
 
-    /// Create a new directory node
-    fn dir(name: String, path: String, size: u64, item_count: u64, children: Vec<DirNode>) -> Self {
+    /// Create a new directory node, aggregating sizes and timestamps from its children
+    fn dir(name: String, path: String, metadata: &fs::Metadata, children: Vec<DirNode>) -> Self {
+        let (own_mtime, own_atime) = timestamps(metadata);
+        let (uid, gid) = ownership(metadata);
         Self {
             name,
             path,
-            size,
-            item_count,
+            size: children.iter().map(|c| c.size).sum(),
+            item_count: children.iter().map(|c| c.item_count).sum(),
+            mtime: children
+                .iter()
+                .map(|c| c.mtime)
+                .fold(own_mtime, Option::max),
+            atime: children
+                .iter()
+                .map(|c| c.atime)
+                .fold(own_atime, Option::max),
+            stale_size: children.iter().map(|c| c.stale_size).sum(),
+            uid,
+            gid,
+            owners: OwnerTotals::of_children(&children),
             children,
             is_file: false,
             has_children: false,
+            incomplete: false,
+            kind: NodeKind::Entry,
+            aggregated_count: 0,
+            symlink_target: None,
         }
     }
 
@@ -60,44 +280,318 @@ impl DirNode {
             children: vec![],
             is_file: false,
             has_children: false,
+            incomplete: false,
+            kind: NodeKind::Entry,
+            aggregated_count: 0,
+            symlink_target: None,
+            mtime: None,
+            atime: None,
+            stale_size: 0,
+            uid: None,
+            gid: None,
+            owners: OwnerTotals::default(),
+        }
+    }
+
+    /// Synthetic node standing in for `merged` siblings. Its path contains a NUL,
+    /// which no real path can, so it never collides with an entry.
+    fn summary(parent_path: &str, merged: &[&DirNode]) -> Self {
+        Self {
+            path: format!("{}\0summary", parent_path),
+            name: format!("{} smaller items", merged.len()),
+            size: merged.iter().map(|c| c.size).sum(),
+            item_count: merged.iter().map(|c| c.item_count).sum(),
+            children: vec![],
+            is_file: true,
+            has_children: false,
+            mtime: merged.iter().map(|c| c.mtime).max().flatten(),
+            atime: merged.iter().map(|c| c.atime).max().flatten(),
+            stale_size: merged.iter().map(|c| c.stale_size).sum(),
+            uid: None,
+            gid: None,
+            owners: OwnerTotals::default(),
+            incomplete: merged.iter().any(|c| c.incomplete),
+            kind: NodeKind::Summary,
+            aggregated_count: merged.len() as u64,
+            symlink_target: None,
+        }
+    }
+
+    /// Whether this is a synthetic node merging several entries
+    pub fn is_summary(&self) -> bool {
+        self.kind == NodeKind::Summary
+    }
+
+    /// Copy of the tree limited to max_depth levels, without cloning deeper nodes
+    pub fn truncated(&self, max_depth: usize) -> DirNode {
+        self.truncated_with(max_depth, &Aggregation::default())
+    }
+
+    /// Truncated copy with small children merged per `aggregation`. Merged nodes
+    /// are summed without being cloned.
+    pub fn truncated_with(&self, max_depth: usize, aggregation: &Aggregation) -> DirNode {
+        let mut node = self.clone_shallow();
+        if max_depth == 0 {
+            node.has_children = self.has_children || !self.children.is_empty();
+        } else {
+            node.children =
+                aggregate_truncated(&self.path, &self.children, max_depth - 1, aggregation);
+        }
+        node
+    }
+
+    /// Clone a node without its children or owner totals
+    pub(crate) fn clone_shallow(&self) -> DirNode {
+        DirNode {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            size: self.size,
+            item_count: self.item_count,
+            children: vec![],
+            is_file: self.is_file,
+            has_children: self.has_children,
+            mtime: self.mtime,
+            atime: self.atime,
+            stale_size: self.stale_size,
+            uid: self.uid,
+            gid: self.gid,
+            owners: OwnerTotals::default(),
+            incomplete: self.incomplete,
+            kind: self.kind,
+            aggregated_count: self.aggregated_count,
+            symlink_target: self.symlink_target.clone(),
         }
     }
 
-    /// Truncate tree to max_depth levels, marking truncated nodes with has_children
-    pub fn truncate_to_depth(&mut self, max_depth: usize) {
-        self.truncate_recursive(0, max_depth);
+    /// Find a descendant (or self) by absolute path
+    pub fn find(&self, path: &Path) -> Option<&DirNode> {
+        let relative = path.strip_prefix(&self.path).ok()?;
+        relative.components().try_fold(self, |node, component| {
+            let name = component.as_os_str().to_str()?;
+            node.children.iter().find(|c| c.name == name)
+        })
     }
 
-    fn truncate_recursive(&mut self, current_depth: usize, max_depth: usize) {
+    /// Insert, replace (`Some`) or remove (`None`) the node at `path`, adjusting sizes
+    /// and item counts up the ancestor chain. Each ancestor whose totals changed is
+    /// passed to `on_ancestor`. Returns false if the parent isn't part of the tree.
+    pub fn replace_descendant(
+        &mut self,
+        path: &Path,
+        node: Option<DirNode>,
+        on_ancestor: &mut impl FnMut(&DirNode),
+    ) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.path) else {
+            return false;
+        };
+        let names: Option<Vec<&str>> = relative
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect();
+        match names {
+            Some(names) if !names.is_empty() => {
+                self.replace_at(&names, node, on_ancestor).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn replace_at(
+        &mut self,
+        names: &[&str],
+        node: Option<DirNode>,
+        on_ancestor: &mut impl FnMut(&DirNode),
+    ) -> Option<(i64, i64, i64)> {
         if self.is_file {
-            return;
+            return None;
         }
 
-        if current_depth >= max_depth {
-            // At max depth - mark as having children if there are any, then clear
-            if !self.children.is_empty() {
-                self.has_children = true;
-                self.children.clear();
+        let index = self.children.iter().position(|c| c.name == names[0]);
+        let totals = |n: &DirNode| (n.size, n.item_count, n.stale_size);
+        let delta = if names.len() == 1 {
+            let (old_size, old_items, old_stale) = index
+                .map(|i| totals(&self.children[i]))
+                .unwrap_or((0, 0, 0));
+            let (new_size, new_items, new_stale) = node.as_ref().map(totals).unwrap_or((0, 0, 0));
+            if let Some(node) = &node {
+                // Removals can't lower the newest time without a rescan, so it only grows
+                self.mtime = self.mtime.max(node.mtime);
+                self.atime = self.atime.max(node.atime);
             }
-        } else {
-            // Not at max depth yet - recurse into children
-            for child in &mut self.children {
-                child.truncate_recursive(current_depth + 1, max_depth);
+
+            match (index, node) {
+                (Some(i), Some(node)) => self.children[i] = node,
+                (None, Some(node)) => self.children.push(node),
+                (Some(i), None) => {
+                    self.children.remove(i);
+                }
+                (None, None) => return Some((0, 0, 0)),
             }
+            (
+                new_size as i64 - old_size as i64,
+                new_items as i64 - old_items as i64,
+                new_stale as i64 - old_stale as i64,
+            )
+        } else {
+            let child = &mut self.children[index?];
+            let delta = child.replace_at(&names[1..], node, on_ancestor)?;
+            self.mtime = self.mtime.max(child.mtime);
+            self.atime = self.atime.max(child.atime);
+            delta
+        };
+        // Ownership can change without the size changing
+        self.owners = OwnerTotals::of_children(&self.children);
+
+        if delta != (0, 0, 0) {
+            self.size = self.size.saturating_add_signed(delta.0);
+            self.item_count = self.item_count.saturating_add_signed(delta.1);
+            self.stale_size = self.stale_size.saturating_add_signed(delta.2);
+            on_ancestor(self);
+        }
+        Some(delta)
+    }
+}
+
+/// Orders for `load_children` and search results
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Size,
+    Name,
+    Path,
+    Modified,
+    ItemCount,
+}
+
+impl SortKey {
+    /// Descending for size, date and count, ascending for name and path
+    pub fn descending_by_default(self) -> bool {
+        matches!(self, SortKey::Size | SortKey::Modified | SortKey::ItemCount)
+    }
+
+    /// Order two nodes by this key, ascending
+    pub fn compare(self, a: &DirNode, b: &DirNode) -> std::cmp::Ordering {
+        match self {
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Path => a.path.cmp(&b.path),
+            SortKey::Modified => a.mtime.cmp(&b.mtime),
+            SortKey::ItemCount => a.item_count.cmp(&b.item_count),
         }
     }
 }
 
+/// Sorting, paging and aggregation for `load_children`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChildrenOptions {
+    #[serde(default)]
+    pub sort: SortKey,
+    /// Defaults to descending for size, date and count, ascending for name
+    #[serde(default)]
+    pub descending: Option<bool>,
+    #[serde(default)]
+    pub offset: usize,
+    /// Every remaining child when absent
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub aggregate: Aggregation,
+    /// Should match the policy of the scan being browsed
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+    /// Should match the scan's `stale_days`, so loaded nodes agree with the scanned tree
+    #[serde(default)]
+    pub stale_days: Option<u64>,
+}
+
+/// One page of a directory's children
+#[derive(Debug, Clone, Serialize)]
+pub struct ChildrenPage {
+    /// Number of children before paging, counting a summary node as one
+    pub total: usize,
+    pub offset: usize,
+    pub items: Vec<DirNode>,
+}
+
+/// Truncated copies of `children` (of the directory at `parent_path`) after aggregation
+fn aggregate_truncated(
+    parent_path: &str,
+    children: &[DirNode],
+    max_depth: usize,
+    aggregation: &Aggregation,
+) -> Vec<DirNode> {
+    let (kept, summary) = aggregation.split(parent_path, children);
+    kept.into_iter()
+        .map(|child| child.truncated_with(max_depth, aggregation))
+        .chain(summary)
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ScanProgress {
     pub path: String,
     pub total_scanned: u64,
+    pub bytes_scanned: u64,
+    pub dirs_scanned: u64,
+    /// Entries that couldn't be read and were skipped
+    pub error_count: u64,
+    pub elapsed_ms: u64,
+    pub files_per_sec: f64,
+    pub bytes_per_sec: f64,
+    /// Used space of the volume, when the scan covers a whole volume
+    pub expected_bytes: Option<u64>,
+    /// Estimated seconds remaining, when `expected_bytes` is known
+    pub eta_secs: Option<u64>,
+}
+
+/// A finished directory near the root, or a file directly below it
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanSubtree {
+    pub parent_path: String,
+    /// Truncated like lazy-loaded children
+    pub node: DirNode,
+}
+
+/// Subtrees finished since the last batch, streamed while the rest of the scan continues
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanSubtrees {
+    pub subtrees: Vec<ScanSubtree>,
+    pub total_scanned: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ScanComplete {
     pub root: DirNode,
     pub total_scanned: u64,
+    pub limits_hit: ScanLimitsHit,
+}
+
+/// Which `ScanConfig` limits cut the scan short
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ScanLimitsHit {
+    /// Some directories at `max_depth` have totals but no child nodes
+    pub max_depth: bool,
+    /// Scanning stopped after `max_entries`; unread directories are `incomplete`
+    pub max_entries: bool,
+    /// Scanning stopped at `timeout_secs`; unread directories are `incomplete`
+    pub timeout: bool,
+}
+
+impl ScanLimitsHit {
+    /// Whether the tree is missing anything because of a limit
+    pub fn any(&self) -> bool {
+        self.max_depth || self.max_entries || self.timeout
+    }
+}
+
+/// Whatever was scanned before a cancellation took effect
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanCancelled {
+    /// Partial tree with unfinished directories marked `incomplete`; absent if
+    /// the scan was cancelled before the root directory was read
+    pub root: Option<DirNode>,
+    pub total_scanned: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -105,23 +599,162 @@ pub struct ScanError {
     pub message: String,
 }
 
+/// Scanner tuning; every field falls back to a default when absent
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScanConfig {
+    /// Worker threads, defaulting to 80% of the available cores
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// Maximum filesystem operations (stat or directory read) per second
+    #[serde(default)]
+    pub max_ops_per_sec: Option<u32>,
+    /// Run scan threads at the lowest CPU and idle I/O priority (Linux only)
+    #[serde(default)]
+    pub low_priority: bool,
+    /// Age in days after which files count towards `stale_size`
+    #[serde(default)]
+    pub stale_days: Option<u64>,
+    /// Directories this many levels below the root keep their totals but not their children
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Stop after this many files and directories
+    #[serde(default)]
+    pub max_entries: Option<u64>,
+    /// Stop after this many seconds, not counting time spent paused
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Merge small children in the tree sent to the UI
+    #[serde(default)]
+    pub aggregate: Aggregation,
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+}
+
+/// 80% of the available cores, leaving headroom for the UI and other work
+pub fn default_thread_count() -> usize {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    (cores * 4 / 5).max(1)
+}
+
+fn build_pool(threads: usize, low_priority: bool) -> Result<ThreadPool, String> {
+    let mut builder = ThreadPoolBuilder::new()
+        .num_threads(threads.max(1))
+        .thread_name(|i| format!("diskly-scan-{}", i));
+    if low_priority {
+        builder = builder.start_handler(|_| throttle::lower_current_thread_priority());
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Cannot create scan threads: {}", e))
+}
+
+/// Pool for default-configured scanners, shared so lazy loads and live updates
+/// don't spawn threads each time
+fn default_pool() -> Arc<ThreadPool> {
+    static POOL: OnceLock<Arc<ThreadPool>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        Arc::new(
+            build_pool(default_thread_count(), false).expect("Failed to create scan thread pool"),
+        )
+    })
+    .clone()
+}
+
+/// Pause flag that scan workers check at directory boundaries
+#[derive(Default)]
+struct PauseGate {
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    /// Workers currently held at the gate
+    parked: AtomicUsize,
+}
+
+/// Hook called with each directory (and its own metadata) once its subtree has been
+/// scanned, and with each file directly below the scan root (with the root's
+/// metadata if the file was restored from a checkpoint)
+pub(crate) type DirCallback<'a> = dyn Fn(&DirNode, &fs::Metadata) + Sync + 'a;
+
+/// Volume and file index of a file, as used by the hard-link tracker
+type FileKey = (u64, u128);
+
 // Core scanner without Tauri dependencies
 #[derive(Clone)]
 pub struct ScannerCore {
     cancelled: Arc<AtomicBool>,
+    pause: Arc<PauseGate>,
     total_scanned: Arc<AtomicU64>,
-    inode_tracker: Arc<DashMap<(u64, u64), PathBuf>>,
-    visited_dirs: Arc<DashSet<(u64, u64)>>,
+    bytes_scanned: Arc<AtomicU64>,
+    /// Device of the scan root; u64::MAX while unknown
+    root_device: Arc<AtomicU64>,
+    /// Part of `bytes_scanned` on the root's device, excluding nested mounts
+    root_device_bytes: Arc<AtomicU64>,
+    dirs_scanned: Arc<AtomicU64>,
+    error_count: Arc<AtomicU64>,
+    inode_tracker: Arc<DashMap<(u64, u128), PathBuf>>,
+    /// Tracked files with other links, by path, with their identity key and
+    /// allocated size for checkpoint records
+    linked_files: Arc<DashMap<String, (FileKey, u64)>>,
+    visited_dirs: Arc<DashSet<(u64, u128)>>,
+    /// Where the inode/file-index keys of the two sets above come from
+    identities: Arc<dyn IdentitySource>,
+    stale_days: Arc<AtomicU64>,
+    /// Files modified before this time (seconds since the epoch) count as stale
+    stale_cutoff: Arc<AtomicU64>,
+    pool: Arc<ThreadPool>,
+    throttle: Option<Arc<Throttle>>,
+    /// Directories from an interrupted scan that can be reused if unchanged
+    checkpoint: Arc<RwLock<Option<Checkpoint>>>,
+    max_depth: Option<usize>,
+    max_entries: Option<u64>,
+    timeout: Option<Duration>,
+    symlinks: SymlinkPolicy,
+    /// Files and directories seen, counted against `max_entries`
+    entries_seen: Arc<AtomicU64>,
+    /// Reference point for `deadline_nanos`, so the deadline fits in an atomic
+    epoch: Instant,
+    /// Nanoseconds after `epoch` at which the scan times out; u64::MAX for none
+    deadline_nanos: Arc<AtomicU64>,
+    /// Nanoseconds after `epoch` at which the scan was paused; u64::MAX while running
+    paused_at_nanos: Arc<AtomicU64>,
+    depth_limit_hit: Arc<AtomicBool>,
+    entry_limit_hit: Arc<AtomicBool>,
+    timed_out: Arc<AtomicBool>,
 }
 
 impl Default for ScannerCore {
     fn default() -> Self {
-        Self {
+        let core = Self {
             cancelled: Arc::new(AtomicBool::new(false)),
+            pause: Arc::new(PauseGate::default()),
             total_scanned: Arc::new(AtomicU64::new(0)),
+            bytes_scanned: Arc::new(AtomicU64::new(0)),
+            root_device: Arc::new(AtomicU64::new(u64::MAX)),
+            root_device_bytes: Arc::new(AtomicU64::new(0)),
+            dirs_scanned: Arc::new(AtomicU64::new(0)),
+            error_count: Arc::new(AtomicU64::new(0)),
             inode_tracker: Arc::new(DashMap::new()),
+            linked_files: Arc::new(DashMap::new()),
             visited_dirs: Arc::new(DashSet::new()),
-        }
+            identities: Arc::new(PlatformIdentities),
+            stale_days: Arc::new(AtomicU64::new(DEFAULT_STALE_DAYS)),
+            stale_cutoff: Arc::new(AtomicU64::new(0)),
+            pool: default_pool(),
+            throttle: None,
+            checkpoint: Arc::new(RwLock::new(None)),
+            max_depth: None,
+            max_entries: None,
+            timeout: None,
+            symlinks: SymlinkPolicy::default(),
+            entries_seen: Arc::new(AtomicU64::new(0)),
+            epoch: Instant::now(),
+            deadline_nanos: Arc::new(AtomicU64::new(u64::MAX)),
+            paused_at_nanos: Arc::new(AtomicU64::new(u64::MAX)),
+            depth_limit_hit: Arc::new(AtomicBool::new(false)),
+            entry_limit_hit: Arc::new(AtomicBool::new(false)),
+            timed_out: Arc::new(AtomicBool::new(false)),
+        };
+        core.update_stale_cutoff();
+        core
     }
 }
 
@@ -130,14 +763,304 @@ impl ScannerCore {
         Self::default()
     }
 
+    /// Scanner with its own thread pool when threads or priority are customised
+    pub fn with_config(config: &ScanConfig) -> Result<Self, String> {
+        let mut core = Self::default();
+        if config.threads.is_some() || config.low_priority {
+            let threads = config.threads.unwrap_or_else(default_thread_count);
+            core.pool = Arc::new(build_pool(threads, config.low_priority)?);
+        }
+        core.throttle = config
+            .max_ops_per_sec
+            .map(|ops| Arc::new(Throttle::new(ops)));
+        if let Some(days) = config.stale_days {
+            core.set_stale_days(days);
+        }
+        core.max_depth = config.max_depth;
+        core.max_entries = config.max_entries;
+        core.timeout = config.timeout_secs.map(Duration::from_secs);
+        core.symlinks = config.symlinks;
+        Ok(core)
+    }
+
+    /// Move onto a thread pool of this scanner's own if it shares the default one.
+    /// Pausing blocks pool threads, which would otherwise hold up lazy loads and
+    /// live updates running on the shared pool.
+    pub fn with_own_pool(mut self) -> Result<Self, String> {
+        if Arc::ptr_eq(&self.pool, &default_pool()) {
+            self.pool = Arc::new(build_pool(default_thread_count(), false)?);
+        }
+        Ok(self)
+    }
+
+    /// Scanner for follow-up scans of this scan's tree, such as the watcher's: same
+    /// settings, pool and hard-link records, but its own cancel, pause and
+    /// progress state and no entry or time limits
+    pub fn fork(&self) -> Self {
+        Self {
+            inode_tracker: self.inode_tracker.clone(),
+            identities: self.identities.clone(),
+            stale_days: self.stale_days.clone(),
+            stale_cutoff: self.stale_cutoff.clone(),
+            pool: self.pool.clone(),
+            throttle: self.throttle.clone(),
+            max_depth: self.max_depth,
+            symlinks: self.symlinks,
+            ..Self::default()
+        }
+    }
+
+    /// Drop hard-link records of files under `path`, so rescanning it counts
+    /// them again instead of treating them as already seen
+    pub fn forget_under(&self, path: &Path) {
+        self.inode_tracker
+            .retain(|_, tracked| !tracked.starts_with(path));
+    }
+
+    /// Scanner that takes file identities from `identities` instead of the platform
+    #[cfg(test)]
+    pub(crate) fn with_identities(mut self, identities: Arc<dyn IdentitySource>) -> Self {
+        self.identities = identities;
+        self
+    }
+
+    /// Wait for the I/O throttle, if one is configured
+    fn pace(&self) {
+        if let Some(throttle) = &self.throttle {
+            throttle.acquire();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn limits_hit(&self) -> ScanLimitsHit {
+        ScanLimitsHit {
+            max_depth: self.depth_limit_hit.load(Ordering::Relaxed),
+            max_entries: self.entry_limit_hit.load(Ordering::Relaxed),
+            timeout: self.timed_out.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether workers should stop: cancelled, or out of entries or time
+    fn should_stop(&self) -> bool {
+        if self.is_cancelled()
+            || self.entry_limit_hit.load(Ordering::Relaxed)
+            || self.timed_out.load(Ordering::Relaxed)
+        {
+            return true;
+        }
+        // The clock stands still while paused, so pausing doesn't run out the timeout
+        let deadline = self.deadline_nanos.load(Ordering::Relaxed);
+        let now = self
+            .elapsed_nanos()
+            .min(self.paused_at_nanos.load(Ordering::Relaxed));
+        if deadline != u64::MAX && now >= deadline {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    fn elapsed_nanos(&self) -> u64 {
+        u64::try_from(self.epoch.elapsed().as_nanos()).unwrap_or(u64::MAX)
+    }
+
+    /// Whether a limit in the config can stop the scan early
+    fn has_limits(&self) -> bool {
+        self.max_depth.is_some() || self.max_entries.is_some() || self.timeout.is_some()
+    }
+
+    /// Count an entry against `max_entries`, returning false once the limit is passed
+    fn admit_entry(&self) -> bool {
+        let seen = self.entries_seen.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.max_entries.is_some_and(|max| seen > max) {
+            self.entry_limit_hit.store(true, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    /// Reuse unchanged directories from an interrupted scan; cleared by the next reset
+    pub fn resume_from(&self, checkpoint: Option<Checkpoint>) {
+        *self.checkpoint.write().expect("Checkpoint lock poisoned") = checkpoint;
+    }
+
+    /// Settings a checkpoint has to be written and resumed with
+    pub(crate) fn checkpoint_settings(&self) -> CheckpointSettings {
+        CheckpointSettings {
+            symlinks: self.symlinks,
+            stale_days: self.stale_days.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Files of a finished directory that have other links, for its checkpoint record
+    pub(crate) fn linked_files(&self, node: &DirNode) -> Vec<LinkedFile> {
+        node.children
+            .iter()
+            .filter(|child| child.is_file)
+            .filter_map(|child| {
+                let entry = self.linked_files.get(&child.path)?;
+                let ((volume, index), size) = *entry;
+                Some(LinkedFile {
+                    name: child.name.clone(),
+                    volume,
+                    index,
+                    size,
+                })
+            })
+            .collect()
+    }
+
     pub fn cancel(&self) {
         self.cancelled.store(true, Ordering::SeqCst);
+        // Paused workers have to wake up to see the cancellation
+        let _paused = self.pause.paused.lock().expect("Pause lock poisoned");
+        self.pause.resumed.notify_all();
+    }
+
+    /// Hold workers at their next directory boundary; work done so far is kept.
+    /// This blocks threads of the scanner's pool, see `with_own_pool`.
+    pub fn pause(&self) {
+        let mut paused = self.pause.paused.lock().expect("Pause lock poisoned");
+        if !*paused {
+            self.paused_at_nanos
+                .store(self.elapsed_nanos(), Ordering::Relaxed);
+        }
+        *paused = true;
+    }
+
+    pub fn resume(&self) {
+        let mut paused = self.pause.paused.lock().expect("Pause lock poisoned");
+        let paused_at = self.paused_at_nanos.swap(u64::MAX, Ordering::Relaxed);
+        if *paused && paused_at != u64::MAX {
+            // Push the deadline back by the time spent paused
+            let paused_for = self.elapsed_nanos().saturating_sub(paused_at);
+            let _ = self.deadline_nanos.fetch_update(
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+                |deadline| (deadline != u64::MAX).then(|| deadline.saturating_add(paused_for)),
+            );
+        }
+        *paused = false;
+        self.pause.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.pause.paused.lock().expect("Pause lock poisoned")
+    }
+
+    fn wait_if_paused(&self) {
+        let mut paused = self.pause.paused.lock().expect("Pause lock poisoned");
+        if !*paused {
+            return;
+        }
+        self.pause.parked.fetch_add(1, Ordering::SeqCst);
+        while *paused && !self.cancelled.load(Ordering::SeqCst) {
+            paused = self
+                .pause
+                .resumed
+                .wait(paused)
+                .expect("Pause lock poisoned");
+        }
+        self.pause.parked.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Number of workers waiting for the scan to be resumed
+    #[cfg(test)]
+    fn parked_workers(&self) -> usize {
+        self.pause.parked.load(Ordering::SeqCst)
     }
 
     pub fn get_total_scanned(&self) -> u64 {
         self.total_scanned.load(Ordering::SeqCst)
     }
 
+    /// Bytes counted so far, after hard-link deduplication
+    pub fn get_bytes_scanned(&self) -> u64 {
+        self.bytes_scanned.load(Ordering::Relaxed)
+    }
+
+    /// Bytes scanned on the scan root's device, leaving out mounts below it
+    pub fn get_root_device_bytes(&self) -> u64 {
+        self.root_device_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn get_dirs_scanned(&self) -> u64 {
+        self.dirs_scanned.load(Ordering::Relaxed)
+    }
+
+    pub fn get_error_count(&self) -> u64 {
+        self.error_count.load(Ordering::Relaxed)
+    }
+
+    /// Age in days after which files count towards `stale_size`
+    pub fn set_stale_days(&self, days: u64) {
+        self.stale_days.store(days, Ordering::SeqCst);
+        self.update_stale_cutoff();
+    }
+
+    fn update_stale_cutoff(&self) {
+        let age = self
+            .stale_days
+            .load(Ordering::SeqCst)
+            .saturating_mul(SECS_PER_DAY);
+        self.stale_cutoff
+            .store(now_secs().saturating_sub(age), Ordering::SeqCst);
+    }
+
+    /// Clear per-scan state before starting a new scan
+    fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.resume();
+        self.clear_progress();
+    }
+
+    /// Clear counters, limits and hard-link records, but not a cancellation
+    fn clear_progress(&self) {
+        self.total_scanned.store(0, Ordering::SeqCst);
+        self.bytes_scanned.store(0, Ordering::Relaxed);
+        self.root_device.store(u64::MAX, Ordering::Relaxed);
+        self.root_device_bytes.store(0, Ordering::Relaxed);
+        self.dirs_scanned.store(0, Ordering::Relaxed);
+        self.error_count.store(0, Ordering::Relaxed);
+        self.entries_seen.store(0, Ordering::Relaxed);
+        self.depth_limit_hit.store(false, Ordering::Relaxed);
+        self.entry_limit_hit.store(false, Ordering::Relaxed);
+        self.timed_out.store(false, Ordering::Relaxed);
+        let deadline = self.timeout.map_or(u64::MAX, |timeout| {
+            u64::try_from(timeout.as_nanos()).map_or(u64::MAX, |timeout| {
+                self.elapsed_nanos().saturating_add(timeout)
+            })
+        });
+        self.deadline_nanos.store(deadline, Ordering::Relaxed);
+        self.inode_tracker.clear();
+        self.linked_files.clear();
+        self.visited_dirs.clear();
+        *self.checkpoint.write().expect("Checkpoint lock poisoned") = None;
+        self.update_stale_cutoff();
+    }
+
+    fn file_node(
+        &self,
+        name: String,
+        path_str: String,
+        path: &Path,
+        metadata: &fs::Metadata,
+        known: Option<FileIdentity>,
+    ) -> DirNode {
+        let size = self.get_file_size(path, metadata, known);
+        let mut node = DirNode::file(name, path_str, size);
+        (node.mtime, node.atime) = timestamps(metadata);
+        (node.uid, node.gid) = ownership(metadata);
+        let cutoff = self.stale_cutoff.load(Ordering::Relaxed);
+        if node.mtime.is_some_and(|mtime| mtime < cutoff) {
+            node.stale_size = node.size;
+        }
+        node
+    }
+
     pub fn scan_directory(&self, path: &Path) -> Result<DirNode, String> {
         if !path.exists() {
             return Err("Path does not exist".to_string());
@@ -147,18 +1070,112 @@ impl ScannerCore {
             return Err("Path is not a directory".to_string());
         }
 
-        // Reset state
-        self.cancelled.store(false, Ordering::SeqCst);
-        self.total_scanned.store(0, Ordering::SeqCst);
-        self.inode_tracker.clear();
-        self.visited_dirs.clear();
+        self.reset();
+        self.pool.install(|| self.scan_recursive(path))
+    }
+
+    /// Scan `path` from scratch like `scan_directory`, except that a cancellation
+    /// requested before the scan starts still applies
+    pub(crate) fn rescan_directory(&self, path: &Path) -> Result<DirNode, String> {
+        self.clear_progress();
+        self.pool.install(|| self.scan_recursive(path))
+    }
 
-        self.scan_recursive(path)
+    /// Scan a file or directory `depth` levels below the root on this scanner's
+    /// pool without resetting state, applying the symlink policy to `path` itself
+    pub(crate) fn scan_subtree(&self, path: &Path, depth: usize) -> Result<DirNode, String> {
+        self.pool.install(|| {
+            let Some((metadata, is_symlink)) = self.stat_path(path) else {
+                return Err("Symlink skipped".to_string());
+            };
+            self.scan_entry(path, metadata, is_symlink, None, depth, &|_, _| {})
+        })
+    }
+
+    /// Stat a path by name under the symlink policy, like a listed entry. Also
+    /// returns whether it's a symlink; `None` for links that are skipped.
+    fn stat_path(&self, path: &Path) -> Option<(std::io::Result<fs::Metadata>, bool)> {
+        self.pace();
+        let lstat = fs::symlink_metadata(path);
+        let is_symlink = lstat.as_ref().is_ok_and(|m| m.file_type().is_symlink());
+        let metadata = match (is_symlink, self.symlinks) {
+            (true, SymlinkPolicy::Follow) => fs::metadata(path),
+            (true, SymlinkPolicy::Skip) => return None,
+            _ => lstat,
+        };
+        Some((metadata, is_symlink))
     }
 
     fn scan_recursive(&self, path: &Path) -> Result<DirNode, String> {
-        if self.cancelled.load(Ordering::SeqCst) {
-            return Err("Scan cancelled".to_string());
+        self.scan_path(path, &|_, _| {})
+    }
+
+    /// Stat a path by name and scan it. `on_dir` is called for every finished
+    /// directory and every file directly below `path`.
+    pub(crate) fn scan_path(&self, path: &Path, on_dir: &DirCallback) -> Result<DirNode, String> {
+        self.pace();
+        let metadata = fs::metadata(path);
+        let device = metadata.as_ref().ok().and_then(|m| {
+            device_of(m, None).or_else(|| Some(self.identities.identify(path, m)?.volume))
+        });
+        if let Some(device) = device {
+            self.root_device.store(device, Ordering::Relaxed);
+        }
+        self.scan_entry(path, metadata, false, None, 0, on_dir)
+    }
+
+    /// Count scanned bytes, and separately those on the root's device
+    fn add_bytes(&self, size: u64, device: Option<u64>) {
+        self.bytes_scanned.fetch_add(size, Ordering::Relaxed);
+        let root = self.root_device.load(Ordering::Relaxed);
+        // Without a device to compare, assume the entry is on the root's
+        if device.is_none_or(|device| root == u64::MAX || device == root) {
+            self.root_device_bytes.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+
+    /// Scan an entry `depth` levels below the scan root. `metadata` is the link's
+    /// or the target's for symlinks, depending on the policy, and `listed` the
+    /// entry's own identity if its directory listing provided one.
+    fn scan_entry(
+        &self,
+        path: &Path,
+        metadata: std::io::Result<fs::Metadata>,
+        is_symlink: bool,
+        listed: Option<FileIdentity>,
+        depth: usize,
+        on_dir: &DirCallback,
+    ) -> Result<DirNode, String> {
+        // A followed link's listed identity is the link's, not the target's
+        let followed = is_symlink && self.symlinks == SymlinkPolicy::Follow;
+        let known = listed.filter(|_| !followed);
+        let mut node = self.scan_target(path, metadata.as_ref(), known, depth, on_dir)?;
+        if is_symlink {
+            node.symlink_target = fs::read_link(path)
+                .ok()
+                .map(|target| target.to_string_lossy().to_string());
+        }
+        // Files directly below the root are streamed too, so that the growing
+        // tree's root totals add up
+        if let (Ok(metadata), true) = (&metadata, depth == 1 && node.is_file) {
+            on_dir(&node, metadata);
+        }
+        Ok(node)
+    }
+
+    fn scan_target(
+        &self,
+        path: &Path,
+        metadata: Result<&fs::Metadata, &std::io::Error>,
+        known: Option<FileIdentity>,
+        depth: usize,
+        on_dir: &DirCallback,
+    ) -> Result<DirNode, String> {
+        if self.should_stop() {
+            return Err("Scan stopped".to_string());
+        }
+        if !self.admit_entry() {
+            return Err("Entry limit reached".to_string());
         }
 
         let name = path
@@ -168,101 +1185,255 @@ impl ScannerCore {
             .to_string();
         let path_str = path.to_string_lossy().to_string();
 
-        let metadata = match fs::metadata(path) {
+        let metadata = match metadata {
             Ok(m) => m,
             Err(_) => {
                 // Skip on permission errors
+                self.error_count.fetch_add(1, Ordering::Relaxed);
                 return Ok(DirNode::empty_dir(name, path_str));
             }
         };
 
-        // Handle files (including symlinks as files)
+        // Handle files, and symlinks that aren't followed
         if !metadata.is_dir() {
-            let size = self.get_file_size(path, &metadata);
+            let node = self.file_node(name, path_str, path, metadata, known);
             self.total_scanned.fetch_add(1, Ordering::SeqCst);
-            return Ok(DirNode::file(name, path_str, size));
+            self.add_bytes(node.size, device_of(metadata, known));
+            return Ok(node);
         }
 
-        // Check for symlink cycles (directories only)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::MetadataExt;
-            let dev = metadata.dev();
-            let ino = metadata.ino();
-            let key = (dev, ino);
-
-            if !self.visited_dirs.insert(key) {
+        // Check for cycles through followed symlinks and junctions (directories only)
+        if let Some(identity) = self.identity(path, metadata, known) {
+            if !self.visited_dirs.insert(identity.key()) {
                 // Already visited this directory, skip to avoid cycle
                 return Ok(DirNode::empty_dir(name, path_str));
             }
         }
 
-        // Read directory entries
-        let entries: Vec<PathBuf> = match fs::read_dir(path) {
-            Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path())).collect(),
-            Err(_) => {
-                // Skip on permission errors
-                return Ok(DirNode::empty_dir(name, path_str));
+        self.wait_if_paused();
+        if self.should_stop() {
+            return Err("Scan stopped".to_string());
+        }
+
+        let (children, expected) = match self.restorable_record(&path_str, metadata) {
+            Some(record) => self.scan_restored(record, metadata, depth, on_dir),
+            None => {
+                let Some(entries) = self.read_entries(path) else {
+                    // Skip on permission errors
+                    self.error_count.fetch_add(1, Ordering::Relaxed);
+                    return Ok(DirNode::empty_dir(name, path_str));
+                };
+                let listed = self.identities.list(path);
+
+                // Scan children in parallel on this scanner's pool
+                let children: Vec<DirNode> = entries
+                    .par_iter()
+                    .filter_map(|entry| {
+                        if self.should_stop() {
+                            return None;
+                        }
+                        let (metadata, is_symlink) = self.entry_metadata(entry);
+                        let identity = listed.get(&entry.file_name()).copied();
+                        self.scan_entry(
+                            &entry.path(),
+                            metadata,
+                            is_symlink,
+                            identity,
+                            depth + 1,
+                            on_dir,
+                        )
+                        .ok()
+                    })
+                    .collect();
+                (children, entries.len())
             }
         };
 
-        // Scan children in parallel using global rayon pool
-        let children: Vec<DirNode> = entries
+        // Entries are only dropped when the scan stops early; keep what was
+        // gathered but don't report the directory as finished
+        let incomplete = children.len() < expected || children.iter().any(|c| c.incomplete);
+        let mut node = DirNode::dir(name, path_str, metadata, children);
+        if incomplete {
+            node.incomplete = true;
+            return Ok(node);
+        }
+
+        // Past the depth limit only totals are kept, so at most one level of
+        // nodes below it exists at a time
+        if self.max_depth.is_some_and(|max| depth >= max) && !node.children.is_empty() {
+            self.depth_limit_hit.store(true, Ordering::Relaxed);
+            node.children = Vec::new();
+            node.has_children = true;
+        }
+
+        self.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+        on_dir(&node, metadata);
+        Ok(node)
+    }
+
+    /// The checkpoint record of a directory whose listing can't have changed since.
+    /// Only the directory itself is checked: its subdirectories are checked when
+    /// the scan enters them.
+    fn restorable_record(
+        &self,
+        path_str: &str,
+        metadata: &fs::Metadata,
+    ) -> Option<CheckpointRecord> {
+        let checkpoint = self.checkpoint.read().expect("Checkpoint lock poisoned");
+        let record = checkpoint.as_ref()?.get(path_str)?;
+        (checkpoint::mtime_nanos(metadata) == Some(record.dir_mtime)).then(|| record.clone())
+    }
+
+    /// Take an unchanged directory's files from its record, and enter its
+    /// subdirectories as if they had just been listed. Returns the children and
+    /// how many there should be.
+    fn scan_restored(
+        &self,
+        record: CheckpointRecord,
+        metadata: &fs::Metadata,
+        depth: usize,
+        on_dir: &DirCallback,
+    ) -> (Vec<DirNode>, usize) {
+        let expected = record.node.children.len() + record.subdirs.len();
+        let device = device_of(metadata, None);
+        let cutoff = self.stale_cutoff.load(Ordering::Relaxed);
+
+        let mut children = record.node.children;
+        for child in children.iter_mut().filter(|child| child.is_file) {
+            if let Some(linked) = record.linked.iter().find(|l| l.name == child.name) {
+                child.size = self.restore_linked(&child.path, linked);
+            }
+            // The cutoff has moved on since the record was written
+            child.stale_size = if child.mtime.is_some_and(|mtime| mtime < cutoff) {
+                child.size
+            } else {
+                0
+            };
+            self.total_scanned.fetch_add(1, Ordering::SeqCst);
+            self.add_bytes(child.size, device);
+            if depth == 0 {
+                // Restored files have no metadata of their own
+                on_dir(child, metadata);
+            }
+        }
+
+        let subdirs: Vec<DirNode> = record
+            .subdirs
             .par_iter()
-            .filter_map(|entry| {
-                if self.cancelled.load(Ordering::SeqCst) {
+            .filter_map(|subdir| {
+                if self.should_stop() {
                     return None;
                 }
-                self.scan_recursive(entry).ok()
+                let path = Path::new(subdir);
+                let (metadata, is_symlink) = self.stat_path(path)?;
+                self.scan_entry(path, metadata, is_symlink, None, depth + 1, on_dir)
+                    .ok()
             })
             .collect();
-
-        // Calculate total size and item count
-        let total_size: u64 = children.iter().map(|c| c.size).sum();
-        let total_items: u64 = children.iter().map(|c| c.item_count).sum();
-
-        Ok(DirNode::dir(
-            name,
-            path_str,
-            total_size,
-            total_items,
-            children,
-        ))
+        children.extend(subdirs);
+        (children, expected)
     }
 
-    #[cfg(unix)]
-    fn get_file_size(&self, path: &Path, metadata: &fs::Metadata) -> u64 {
-        use std::os::unix::fs::MetadataExt;
-
-        let dev = metadata.dev();
-        let ino = metadata.ino();
-        let nlink = metadata.nlink();
+    /// Count a restored file that has other links the way `get_file_size` would:
+    /// only if none of its links has been counted yet
+    fn restore_linked(&self, path_str: &str, linked: &LinkedFile) -> u64 {
+        let key = (linked.volume, linked.index);
+        self.linked_files
+            .insert(path_str.to_string(), (key, linked.size));
+        match self.inode_tracker.entry(key) {
+            dashmap::Entry::Occupied(_) => 0,
+            dashmap::Entry::Vacant(entry) => {
+                entry.insert(PathBuf::from(path_str));
+                linked.size
+            }
+        }
+    }
 
-        // Handle hard links - count only once
-        if nlink > 1 {
-            let key = (dev, ino);
+    fn read_entries(&self, path: &Path) -> Option<Vec<fs::DirEntry>> {
+        self.pace();
+        let entries = fs::read_dir(path).ok()?;
+        Some(
+            entries
+                .filter_map(|e| {
+                    // Entries that can't be listed are dropped, but counted
+                    e.inspect_err(|_| {
+                        self.error_count.fetch_add(1, Ordering::Relaxed);
+                    })
+                    .ok()
+                })
+                .filter(|e| self.keep_entry(e))
+                .collect(),
+        )
+    }
 
-            if self.inode_tracker.contains_key(&key) {
-                return 0; // Already counted this inode at a different path
-            }
+    /// Whether a listed entry is part of the scan under the symlink policy
+    fn keep_entry(&self, entry: &fs::DirEntry) -> bool {
+        self.symlinks != SymlinkPolicy::Skip || !is_symlink(entry)
+    }
 
-            self.inode_tracker.insert(key, path.to_path_buf());
+    /// Stat an entry relative to its open directory instead of by full path: on
+    /// Linux this is statx/fstatat on the directory fd, on Windows it's free from
+    /// the enumeration data. Only followed symlinks need a stat by path; the
+    /// directory itself is still opened by full path in `read_entries`.
+    /// Also returns whether the entry is a symlink.
+    fn entry_metadata(&self, entry: &fs::DirEntry) -> (std::io::Result<fs::Metadata>, bool) {
+        self.pace();
+        if !is_symlink(entry) {
+            return (entry.metadata(), false);
         }
+        let metadata = match self.symlinks {
+            SymlinkPolicy::Follow => fs::metadata(entry.path()),
+            // Skipped links are already filtered out of the listing
+            SymlinkPolicy::Link | SymlinkPolicy::Skip => entry.metadata(),
+        };
+        (metadata, true)
+    }
 
-        // Use actual disk usage (blocks * 512) for sparse files
-        // blocks() returns 512-byte blocks, not filesystem blocks
-        metadata.blocks() * 512
+    /// The entry's identity from its directory listing, or looked up by path
+    fn identity(
+        &self,
+        path: &Path,
+        metadata: &fs::Metadata,
+        known: Option<FileIdentity>,
+    ) -> Option<FileIdentity> {
+        known.or_else(|| self.identities.identify(path, metadata))
     }
 
-    #[cfg(not(unix))]
-    fn get_file_size(&self, _path: &Path, metadata: &fs::Metadata) -> u64 {
-        metadata.len()
+    fn get_file_size(
+        &self,
+        path: &Path,
+        metadata: &fs::Metadata,
+        known: Option<FileIdentity>,
+    ) -> u64 {
+        let size = allocated_size(metadata);
+        if size == 0 {
+            return 0; // Nothing to double count, so skip the identity lookup
+        }
+
+        // Handle hard links - count only once. Followed symlinks can reach any
+        // file a second time, so under Follow every file is tracked.
+        let track_all = self.symlinks == SymlinkPolicy::Follow;
+        match self.identity(path, metadata, known) {
+            Some(identity) if track_all || identity.may_be_shared() => {
+                if identity.may_be_shared() {
+                    self.linked_files
+                        .insert(path.to_string_lossy().to_string(), (identity.key(), size));
+                }
+                match self.inode_tracker.entry(identity.key()) {
+                    // Already counted this file at a different path
+                    dashmap::Entry::Occupied(_) => 0,
+                    dashmap::Entry::Vacant(entry) => {
+                        entry.insert(path.to_path_buf());
+                        size
+                    }
+                }
+            }
+            _ => size,
+        }
     }
 }
 
 // Tauri wrapper with event emission
-use std::sync::Mutex;
-use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
 #[derive(Clone)]
@@ -270,24 +1441,95 @@ pub struct Scanner {
     core: ScannerCore,
     app: AppHandle,
     last_progress_emit: Arc<Mutex<Instant>>,
+    /// Subtrees waiting for the next progress event to be sent with
+    pending_subtrees: Arc<Mutex<Vec<ScanSubtree>>>,
+    started: Arc<Mutex<Instant>>,
+    /// When the running scan was paused, excluded from elapsed time on resume
+    paused_at: Arc<Mutex<Option<Instant>>>,
+    /// Used space of the volume being scanned, for the ETA
+    expected_bytes: Arc<Mutex<Option<u64>>>,
+    /// Applied to the trees sent with scan events
+    aggregation: Aggregation,
+    /// Set once a newer scan replaces this one, whose events would confuse the UI
+    superseded: Arc<AtomicBool>,
 }
 
 impl Scanner {
     const PROGRESS_THROTTLE_MS: u64 = 50;
 
     pub fn new(app: AppHandle) -> Self {
-        Self {
-            core: ScannerCore::new(),
+        Self::with_config(app, &ScanConfig::default()).expect("Failed to create scan thread pool")
+    }
+
+    /// Each scan runs on its own pool so that pausing it only holds up the scan
+    pub fn with_config(app: AppHandle, config: &ScanConfig) -> Result<Self, String> {
+        Ok(Self {
+            core: ScannerCore::with_config(config)?.with_own_pool()?,
             app,
             last_progress_emit: Arc::new(Mutex::new(Instant::now())),
-        }
+            pending_subtrees: Arc::new(Mutex::new(Vec::new())),
+            started: Arc::new(Mutex::new(Instant::now())),
+            paused_at: Arc::new(Mutex::new(None)),
+            expected_bytes: Arc::new(Mutex::new(None)),
+            aggregation: config.aggregate,
+            superseded: Arc::new(AtomicBool::new(false)),
+        })
     }
 
     pub fn cancel(&self) {
         self.core.cancel();
     }
 
-    pub fn scan_directory(&self, path: String) -> Result<(), String> {
+    /// Cancel this scan without sending any further events, as a newer scan
+    /// has taken its place
+    pub fn supersede(&self) {
+        self.superseded.store(true, Ordering::SeqCst);
+        self.core.cancel();
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if !self.superseded.load(Ordering::SeqCst) {
+            let _ = self.app.emit(event, payload);
+        }
+    }
+
+    /// Scanner for follow-up scans of the last scan's tree, see `ScannerCore::fork`
+    pub fn fork_core(&self) -> ScannerCore {
+        self.core.fork()
+    }
+
+    /// Limits the last scan stopped at
+    pub fn limits_hit(&self) -> ScanLimitsHit {
+        self.core.limits_hit()
+    }
+
+    pub fn pause(&self) {
+        let mut paused_at = self.paused_at.lock().expect("Pause time lock poisoned");
+        paused_at.get_or_insert_with(Instant::now);
+        self.core.pause();
+    }
+
+    pub fn resume(&self) {
+        // Shift the start time so rates and the ETA ignore the paused period
+        if let Some(paused_at) = self
+            .paused_at
+            .lock()
+            .expect("Pause time lock poisoned")
+            .take()
+        {
+            *self.started.lock().expect("Scan start lock poisoned") += paused_at.elapsed();
+        }
+        self.core.resume();
+    }
+
+    /// Scan and emit events. The full (untruncated) tree of a completed scan is
+    /// handed to `publish` before `scan:complete` goes out, so that commands the
+    /// UI sends in response already find it.
+    pub fn scan_directory(
+        &self,
+        path: String,
+        publish: impl FnOnce(DirNode),
+    ) -> Result<(), String> {
         let path_buf = PathBuf::from(&path);
 
         if !path_buf.exists() {
@@ -298,95 +1540,128 @@ impl Scanner {
             return Err("Path is not a directory".to_string());
         }
 
-        // Reset state
-        self.core.cancelled.store(false, Ordering::SeqCst);
-        self.core.total_scanned.store(0, Ordering::SeqCst);
-        self.core.inode_tracker.clear();
-        self.core.visited_dirs.clear();
+        self.core.reset();
+        self.pending_subtrees
+            .lock()
+            .expect("Pending subtrees lock poisoned")
+            .clear();
+        *self.started.lock().expect("Scan start lock poisoned") = Instant::now();
+        *self.paused_at.lock().expect("Pause time lock poisoned") = None;
+        *self
+            .expected_bytes
+            .lock()
+            .expect("Expected bytes lock poisoned") = crate::volumes::used_space_at(&path_buf);
+
+        // Limited scans stop on purpose, and depth-limited trees lack the nodes a
+        // full scan would need to resume from, so only unlimited scans checkpoint.
+        // Followed links can reach a restored file again without it being
+        // recognised, so those scans don't either.
+        let checkpointed = !self.core.has_limits() && self.core.symlinks != SymlinkPolicy::Follow;
+        let writer = if checkpointed {
+            let settings = self.core.checkpoint_settings();
+            self.core.resume_from(Checkpoint::load(&path_buf, settings));
+            CheckpointWriter::create(&path_buf, settings)
+                .inspect_err(|e| eprintln!("Checkpointing disabled: {}", e))
+                .ok()
+        } else {
+            None
+        };
 
-        match self.scan_with_events(&path_buf) {
-            Ok(mut root) => {
-                let total = self.core.get_total_scanned();
-                // Truncate to 3 levels for initial load (lazy loading)
-                root.truncate_to_depth(3);
-                let _ = self.app.emit(
-                    "scan:complete",
-                    ScanComplete {
-                        root,
-                        total_scanned: total,
+        let result = self
+            .core
+            .pool
+            .install(|| self.scan_with_events(&path_buf, writer.as_ref()));
+        // Release the loaded checkpoint's memory
+        self.core.resume_from(None);
+        if let Some(writer) = writer {
+            if result.as_ref().is_ok_and(|root| !root.incomplete) {
+                writer.discard();
+            } else {
+                writer.flush();
+            }
+        }
+
+        match result {
+            // The partial tree only goes to the UI; callers never store it as a result
+            Ok(root) if root.incomplete && self.core.is_cancelled() => {
+                self.emit(
+                    "scan:cancelled",
+                    ScanCancelled {
+                        root: Some(root.truncated_with(3, &self.aggregation)),
+                        total_scanned: self.core.get_total_scanned(),
+                    },
+                );
+                Err("Scan cancelled".to_string())
+            }
+            Err(_) if self.core.is_cancelled() => {
+                self.emit(
+                    "scan:cancelled",
+                    ScanCancelled {
+                        root: None,
+                        total_scanned: self.core.get_total_scanned(),
                     },
                 );
+                Err("Scan cancelled".to_string())
+            }
+            Ok(root) => {
+                // Truncate to 3 levels for initial load (lazy loading)
+                let complete = ScanComplete {
+                    root: root.truncated_with(3, &self.aggregation),
+                    total_scanned: self.core.get_total_scanned(),
+                    limits_hit: self.core.limits_hit(),
+                };
+                publish(root);
+                self.emit("scan:complete", complete);
                 Ok(())
             }
             Err(e) => {
-                let _ = self
-                    .app
-                    .emit("scan:error", ScanError { message: e.clone() });
+                self.emit("scan:error", ScanError { message: e.clone() });
                 Err(e)
             }
         }
     }
 
-    fn scan_with_events(&self, path: &Path) -> Result<DirNode, String> {
-        if self.core.cancelled.load(Ordering::SeqCst) {
-            return Err("Scan cancelled".to_string());
-        }
-
-        let name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        let path_str = path.to_string_lossy().to_string();
-
-        let metadata = match fs::metadata(path) {
-            Ok(m) => m,
-            Err(_) => {
-                return Ok(DirNode::empty_dir(name, path_str));
+    fn scan_with_events(
+        &self,
+        path: &Path,
+        checkpoint: Option<&CheckpointWriter>,
+    ) -> Result<DirNode, String> {
+        self.core.scan_path(path, &|node, metadata| {
+            if let Some(checkpoint) = checkpoint {
+                checkpoint.record(node, metadata, self.core.linked_files(node));
             }
-        };
-
-        if !metadata.is_dir() {
-            let size = self.core.get_file_size(path, &metadata);
-            self.core.total_scanned.fetch_add(1, Ordering::SeqCst);
-            return Ok(DirNode::file(name, path_str, size));
-        }
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::MetadataExt;
-            let dev = metadata.dev();
-            let ino = metadata.ino();
-            let key = (dev, ino);
+            self.queue_subtree(path, node);
+            self.emit_progress(node);
+        })
+    }
 
-            if !self.core.visited_dirs.insert(key) {
-                return Ok(DirNode::empty_dir(name, path_str));
-            }
+    /// Queue a finished directory near the root, or a file directly below it, to
+    /// be streamed with the next progress event so the UI can grow the tree
+    fn queue_subtree(&self, root: &Path, node: &DirNode) {
+        let node_path = Path::new(&node.path);
+        let depth = node_path
+            .strip_prefix(root)
+            .map_or(0, |relative| relative.components().count());
+        if depth == 0 || depth > STREAM_DEPTH {
+            return;
         }
 
-        let entries: Vec<PathBuf> = match fs::read_dir(path) {
-            Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path())).collect(),
-            Err(_) => {
-                return Ok(DirNode::empty_dir(name, path_str));
-            }
+        let Some(parent) = node_path.parent() else {
+            return;
         };
+        let subtree = ScanSubtree {
+            parent_path: parent.to_string_lossy().to_string(),
+            node: node.truncated_with(LAZY_LOAD_DEPTH, &self.aggregation),
+        };
+        self.pending_subtrees
+            .lock()
+            .expect("Pending subtrees lock poisoned")
+            .push(subtree);
+    }
 
-        let children: Vec<DirNode> = entries
-            .par_iter()
-            .filter_map(|entry| {
-                if self.core.cancelled.load(Ordering::SeqCst) {
-                    return None;
-                }
-                self.scan_with_events(entry).ok()
-            })
-            .collect();
-
-        let total_size: u64 = children.iter().map(|c| c.size).sum();
-        let total_items: u64 = children.iter().map(|c| c.item_count).sum();
-
-        let node = DirNode::dir(name, path_str, total_size, total_items, children);
-
-        // Emit progress event (throttled)
+    /// Emit a progress event, and any queued subtrees, for a finished directory
+    /// (throttled, so that thousands of subtrees don't flood IPC)
+    fn emit_progress(&self, node: &DirNode) {
         let should_emit = {
             let mut last_emit = self
                 .last_progress_emit
@@ -401,24 +1676,79 @@ impl Scanner {
         };
 
         if should_emit {
-            let total = self.core.get_total_scanned();
-            let _ = self.app.emit(
-                "scan:directory_complete",
-                ScanProgress {
-                    path: path.to_string_lossy().to_string(),
-                    total_scanned: total,
-                },
+            self.emit("scan:directory_complete", self.progress(&node.path));
+
+            let subtrees = std::mem::take(
+                &mut *self
+                    .pending_subtrees
+                    .lock()
+                    .expect("Pending subtrees lock poisoned"),
             );
+            if !subtrees.is_empty() {
+                self.emit(
+                    "scan:subtrees",
+                    ScanSubtrees {
+                        subtrees,
+                        total_scanned: self.core.get_total_scanned(),
+                    },
+                );
+            }
         }
+    }
 
-        Ok(node)
+    fn progress(&self, path: &str) -> ScanProgress {
+        let total_scanned = self.core.get_total_scanned();
+        let bytes_scanned = self.core.get_bytes_scanned();
+        let elapsed = self
+            .started
+            .lock()
+            .expect("Scan start lock poisoned")
+            .elapsed();
+        let secs = elapsed.as_secs_f64();
+        let (files_per_sec, bytes_per_sec) = if secs > 0.0 {
+            (total_scanned as f64 / secs, bytes_scanned as f64 / secs)
+        } else {
+            (0.0, 0.0)
+        };
+
+        let expected_bytes = *self
+            .expected_bytes
+            .lock()
+            .expect("Expected bytes lock poisoned");
+        // Based on average throughput on the root's volume so far, since mounts
+        // below it don't fill its used space; clamps to zero once the scan passes
+        // that (hard links, writes during the scan)
+        let root_device_bytes = self.core.get_root_device_bytes();
+        let root_bytes_per_sec = if secs > 0.0 {
+            root_device_bytes as f64 / secs
+        } else {
+            0.0
+        };
+        let eta_secs = expected_bytes
+            .filter(|_| root_bytes_per_sec > 0.0)
+            .map(|expected| {
+                (expected.saturating_sub(root_device_bytes) as f64 / root_bytes_per_sec) as u64
+            });
+
+        ScanProgress {
+            path: path.to_string(),
+            total_scanned,
+            bytes_scanned,
+            dirs_scanned: self.core.get_dirs_scanned(),
+            error_count: self.core.get_error_count(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            files_per_sec,
+            bytes_per_sec,
+            expected_bytes,
+            eta_secs,
+        }
     }
 }
 
-/// Load children for a directory (lazy loading).
-/// Returns direct children with their sizes already calculated,
-/// truncated to LAZY_LOAD_DEPTH levels.
-pub fn load_children(path: &Path) -> Result<Vec<DirNode>, String> {
+/// Load children for a directory (lazy loading) by scanning it from disk.
+/// Returns one sorted page of direct children with their sizes already
+/// calculated, truncated to LAZY_LOAD_DEPTH levels.
+pub fn load_children(path: &Path, options: &ChildrenOptions) -> Result<ChildrenPage, String> {
     if !path.exists() {
         return Err("Path does not exist".to_string());
     }
@@ -427,22 +1757,531 @@ pub fn load_children(path: &Path) -> Result<Vec<DirNode>, String> {
         return Err("Path is not a directory".to_string());
     }
 
-    let scanner = ScannerCore::new();
+    let mut scanner = ScannerCore::new();
+    scanner.symlinks = options.symlinks;
+    if let Some(days) = options.stale_days {
+        scanner.set_stale_days(days);
+    }
 
-    let entries: Vec<PathBuf> = match fs::read_dir(path) {
-        Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path())).collect(),
+    let entries: Vec<fs::DirEntry> = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| scanner.keep_entry(e))
+            .collect(),
         Err(e) => return Err(format!("Cannot read directory: {}", e)),
     };
 
-    let mut children: Vec<DirNode> = entries
-        .par_iter()
-        .filter_map(|entry| scanner.scan_recursive(entry).ok())
+    let listed = scanner.identities.list(path);
+    let children: Vec<DirNode> = scanner.pool.install(|| {
+        entries
+            .par_iter()
+            .filter_map(|entry| {
+                let (metadata, is_symlink) = scanner.entry_metadata(entry);
+                let identity = listed.get(&entry.file_name()).copied();
+                scanner
+                    .scan_entry(&entry.path(), metadata, is_symlink, identity, 1, &|_, _| {})
+                    .ok()
+            })
+            .collect()
+    });
+
+    Ok(page_children(&path.to_string_lossy(), &children, options))
+}
+
+/// One page of the children of an already scanned directory, or `None` if the
+/// node doesn't hold all of its children (a file, or a directory collapsed by
+/// a depth limit or left unfinished) and they have to be read from disk
+pub fn children_page(node: &DirNode, options: &ChildrenOptions) -> Option<ChildrenPage> {
+    let collapsed = node.has_children && node.children.is_empty();
+    if node.is_file || node.incomplete || collapsed {
+        return None;
+    }
+    Some(page_children(&node.path, &node.children, options))
+}
+
+fn page_children(
+    parent_path: &str,
+    children: &[DirNode],
+    options: &ChildrenOptions,
+) -> ChildrenPage {
+    // A summary node always sorts last, whatever the order of the rest
+    let aggregation = &options.aggregate;
+    let (mut kept, summary) = aggregation.split(parent_path, children);
+    let descending = options
+        .descending
+        .unwrap_or(options.sort.descending_by_default());
+    // Ties fall back to the name so pages stay consistent between requests
+    kept.sort_by(|a, b| {
+        let ordering = options.sort.compare(a, b);
+        let ordering = if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        ordering.then_with(|| a.name.cmp(&b.name))
+    });
+
+    let total = kept.len() + summary.iter().len();
+    let limit = options.limit.unwrap_or(usize::MAX);
+    // Only the requested page is copied
+    let mut items: Vec<DirNode> = kept
+        .iter()
+        .skip(options.offset)
+        .take(limit)
+        .map(|child| child.truncated_with(LAZY_LOAD_DEPTH, aggregation))
         .collect();
+    let summary_index = kept.len();
+    if summary_index >= options.offset && summary_index - options.offset < limit {
+        items.extend(summary);
+    }
+
+    ChildrenPage {
+        total,
+        offset: options.offset,
+        items,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    /// Directory with `dirs` subdirectories of `files` files each
+    fn disk_tree(dirs: usize, files: usize) -> TempDir {
+        let root = TempDir::new().unwrap();
+        for d in 0..dirs {
+            let dir = root.path().join(format!("dir{}", d));
+            fs::create_dir(&dir).unwrap();
+            for f in 0..files {
+                fs::write(dir.join(format!("file{}", f)), b"data").unwrap();
+            }
+        }
+        root
+    }
+
+    /// Run `f` on another thread, giving up on it after `secs`
+    fn within<T: Send + 'static>(secs: u64, f: impl FnOnce() -> T + Send + 'static) -> Option<T> {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(f());
+        });
+        rx.recv_timeout(Duration::from_secs(secs)).ok()
+    }
+
+    fn file(name: &str, size: u64) -> DirNode {
+        DirNode::file(name.to_string(), format!("/p/{}", name), size)
+    }
+
+    fn dir(children: Vec<DirNode>) -> DirNode {
+        let mut node = DirNode::empty_dir("p".to_string(), "/p".to_string());
+        node.size = children.iter().map(|c| c.size).sum();
+        node.item_count = children.len() as u64;
+        node.children = children;
+        node
+    }
+
+    fn names(page: &ChildrenPage) -> Vec<&str> {
+        page.items.iter().map(|node| node.name.as_str()).collect()
+    }
+
+    fn sample() -> DirNode {
+        dir(vec![
+            file("d", 3),
+            file("f", 1),
+            file("b", 3),
+            file("e", 5),
+            file("a", 3),
+            file("c", 3),
+        ])
+    }
+
+    #[test]
+    fn pages_follow_one_stable_order() {
+        let node = sample();
+        let mut paged = Vec::new();
+        for offset in (0..6).step_by(2) {
+            let options = ChildrenOptions {
+                offset,
+                limit: Some(2),
+                ..Default::default()
+            };
+            let page = children_page(&node, &options).unwrap();
+            assert_eq!(page.total, 6);
+            assert_eq!(page.offset, offset);
+            paged.extend(page.items.into_iter().map(|n| n.name));
+        }
+        // Equal sizes are ordered by name
+        assert_eq!(paged, ["e", "a", "b", "c", "d", "f"]);
+
+        let options = ChildrenOptions {
+            sort: SortKey::Name,
+            descending: Some(true),
+            ..Default::default()
+        };
+        let page = children_page(&node, &options).unwrap();
+        assert_eq!(names(&page), ["f", "e", "d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn offset_past_the_end_gives_an_empty_page() {
+        let options = ChildrenOptions {
+            offset: 10,
+            limit: Some(5),
+            ..Default::default()
+        };
+        let page = children_page(&sample(), &options).unwrap();
+        assert_eq!(page.total, 6);
+        assert_eq!(page.offset, 10);
+        assert!(page.items.is_empty());
+    }
+
+    #[test]
+    fn summary_comes_last_on_the_final_page() {
+        let aggregate = Aggregation {
+            min_size: None,
+            top_k: Some(2),
+        };
+        let page = |offset, limit| {
+            let options = ChildrenOptions {
+                offset,
+                limit: Some(limit),
+                aggregate,
+                ..Default::default()
+            };
+            children_page(&sample(), &options).unwrap()
+        };
+        assert_eq!(page(0, 2).total, 3);
+        assert_eq!(names(&page(0, 2)), ["e", "a"]);
+        assert_eq!(names(&page(2, 2)), ["4 smaller items"]);
+        assert!(page(3, 2).items.is_empty());
+    }
+
+    fn split_names(aggregation: Aggregation, node: &DirNode) -> (Vec<&str>, Option<DirNode>) {
+        let (kept, summary) = aggregation.split(&node.path, &node.children);
+        (kept.iter().map(|n| n.name.as_str()).collect(), summary)
+    }
 
-    // Truncate each child to LAZY_LOAD_DEPTH levels
-    for child in &mut children {
-        child.truncate_to_depth(LAZY_LOAD_DEPTH);
+    #[test]
+    fn children_below_the_size_threshold_are_merged() {
+        let aggregation = Aggregation {
+            min_size: Some(3),
+            top_k: None,
+        };
+        let node = sample();
+        let (kept, summary) = split_names(aggregation, &node);
+        // A single small child isn't worth a summary
+        assert_eq!(kept, ["e", "a", "b", "c", "d", "f"]);
+        assert!(summary.is_none());
+
+        let aggregation = Aggregation {
+            min_size: Some(4),
+            top_k: None,
+        };
+        let (kept, summary) = split_names(aggregation, &node);
+        assert_eq!(kept, ["e"]);
+        let summary = summary.unwrap();
+        assert_eq!(summary.name, "5 smaller items");
+        assert_eq!(summary.aggregated_count, 5);
+        assert_eq!(summary.size, 13);
+        assert_eq!(summary.item_count, 5);
+    }
+
+    #[test]
+    fn children_beyond_the_top_k_are_merged() {
+        let aggregation = Aggregation {
+            min_size: None,
+            top_k: Some(3),
+        };
+        let node = sample();
+        let (kept, summary) = split_names(aggregation, &node);
+        assert_eq!(kept, ["e", "a", "b"]);
+        assert_eq!(summary.unwrap().aggregated_count, 3);
+
+        // Both limits apply; whichever cuts first wins
+        let aggregation = Aggregation {
+            min_size: Some(4),
+            top_k: Some(3),
+        };
+        assert_eq!(split_names(aggregation, &node).0, ["e"]);
+
+        let (kept, summary) = split_names(Aggregation::default(), &node);
+        assert_eq!(kept.len(), 6);
+        assert!(summary.is_none());
+    }
+
+    #[test]
+    fn summary_path_cannot_collide_with_an_entry() {
+        let mut node = sample();
+        node.children.push(file("4 smaller items", 0));
+        let aggregation = Aggregation {
+            min_size: None,
+            top_k: Some(3),
+        };
+        let summary = split_names(aggregation, &node).1.unwrap();
+        assert!(summary.is_summary());
+        assert!(summary.path.contains('\0'));
+        assert!(node.children.iter().all(|c| c.path != summary.path));
+        assert!(node.find(Path::new(&summary.path)).is_none());
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["kind"], "summary");
+        let json = serde_json::to_value(file("a", 1)).unwrap();
+        assert!(json.get("kind").is_none());
+    }
+
+    #[test]
+    fn nodes_without_all_children_are_loaded_from_disk() {
+        let options = ChildrenOptions::default();
+        assert!(children_page(&file("a", 1), &options).is_none());
+
+        let mut collapsed = dir(vec![]);
+        collapsed.has_children = true;
+        assert!(children_page(&collapsed, &options).is_none());
+
+        let mut unfinished = sample();
+        unfinished.incomplete = true;
+        assert!(children_page(&unfinished, &options).is_none());
+
+        assert!(children_page(&dir(vec![]), &options).is_some());
     }
 
-    Ok(children)
+    #[test]
+    fn loaded_children_use_the_scan_stale_cutoff() {
+        let tree = TempDir::new().unwrap();
+        fs::write(tree.path().join("f"), vec![1u8; 4096]).unwrap();
+
+        let stale_size = |stale_days| {
+            let options = ChildrenOptions {
+                stale_days,
+                ..Default::default()
+            };
+            load_children(tree.path(), &options).unwrap().items[0].stale_size
+        };
+        // Just written, so only stale when everything older than now is
+        assert_eq!(stale_size(None), 0);
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(stale_size(Some(0)) > 0);
+    }
+
+    #[test]
+    fn paused_scan_keeps_its_progress_and_finishes_on_resume() {
+        let tree = disk_tree(16, 5);
+        // A single worker, so nothing else is read once it's held at the gate
+        let config = ScanConfig {
+            threads: Some(1),
+            ..Default::default()
+        };
+        let core = ScannerCore::with_config(&config).unwrap();
+
+        // Pause from inside the scan once the first directory is done
+        let scan = {
+            let core = core.clone();
+            let root = tree.path().to_path_buf();
+            std::thread::spawn(move || core.scan_path(&root, &|_, _| core.pause()))
+        };
+        while core.parked_workers() == 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(core.get_total_scanned(), 5);
+        assert_eq!(core.get_dirs_scanned(), 1);
+        assert!(!scan.is_finished());
+
+        // The callback pauses again after every directory; keep resuming
+        while !scan.is_finished() {
+            core.resume();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let root = scan.join().unwrap().unwrap();
+        assert!(!root.incomplete);
+        assert_eq!(root.item_count, 80);
+        assert_eq!(core.get_total_scanned(), 80);
+        assert_eq!(core.get_dirs_scanned(), 17);
+    }
+
+    #[test]
+    fn cancel_wakes_paused_workers() {
+        let tree = disk_tree(4, 4);
+        let core = ScannerCore::new().with_own_pool().unwrap();
+        core.pause();
+
+        let scan = {
+            let core = core.clone();
+            let root = tree.path().to_path_buf();
+            std::thread::spawn(move || core.scan_subtree(&root, 0))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!scan.is_finished());
+
+        core.cancel();
+        let result = within(5, move || scan.join().unwrap()).expect("cancel woke the scan");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn cancelled_scan_keeps_finished_directories_and_marks_the_rest() {
+        let tree = TempDir::new().unwrap();
+        for dir in ["a/x", "a/y", "b/z", "b/w"] {
+            let dir = tree.path().join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("f"), b"data").unwrap();
+        }
+        let config = ScanConfig {
+            threads: Some(1),
+            ..Default::default()
+        };
+        let core = ScannerCore::with_config(&config).unwrap();
+        core.reset();
+
+        // Cancel as soon as the first directory is finished, before its sibling
+        let finished = Mutex::new(None);
+        let root = core
+            .pool
+            .install(|| {
+                core.scan_path(tree.path(), &|node, _| {
+                    finished.lock().unwrap().get_or_insert(node.path.clone());
+                    core.cancel();
+                })
+            })
+            .unwrap();
+
+        let finished = finished
+            .into_inner()
+            .unwrap()
+            .expect("a directory finished");
+        let node = root.find(Path::new(&finished)).expect("kept in the tree");
+        assert!(!node.incomplete);
+        assert_eq!(node.item_count, 1);
+        let parent = root.find(Path::new(&finished).parent().unwrap()).unwrap();
+        assert!(parent.incomplete);
+        assert!(root.incomplete);
+        assert!(root.item_count < 4);
+    }
+
+    #[test]
+    fn rescans_keep_an_earlier_cancellation() {
+        let tree = TempDir::new().unwrap();
+        fs::write(tree.path().join("f"), b"data").unwrap();
+
+        let core = ScannerCore::new();
+        core.cancel();
+        assert!(core.rescan_directory(tree.path()).is_err());
+        // A new scan starts over
+        assert!(core.scan_directory(tree.path()).is_ok());
+    }
+
+    #[test]
+    fn bytes_on_the_root_device_are_counted_for_the_eta() {
+        let tree = TempDir::new().unwrap();
+        fs::create_dir(tree.path().join("sub")).unwrap();
+        fs::write(tree.path().join("sub/f"), vec![1u8; 64 * 1024]).unwrap();
+
+        let core = ScannerCore::new();
+        core.scan_directory(tree.path()).unwrap();
+        assert!(core.get_bytes_scanned() > 0);
+        assert_eq!(core.get_root_device_bytes(), core.get_bytes_scanned());
+    }
+
+    #[test]
+    fn files_below_the_root_are_reported_with_directories() {
+        let tree = TempDir::new().unwrap();
+        fs::create_dir(tree.path().join("sub")).unwrap();
+        fs::write(tree.path().join("top"), b"data").unwrap();
+        fs::write(tree.path().join("sub/nested"), b"data").unwrap();
+
+        let core = ScannerCore::new();
+        let reported = Mutex::new(Vec::new());
+        core.scan_path(tree.path(), &|node, _| {
+            reported
+                .lock()
+                .unwrap()
+                .push((node.name.clone(), node.is_file));
+        })
+        .unwrap();
+
+        let mut reported = reported.into_inner().unwrap();
+        reported.sort();
+        let root_name = tree
+            .path()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut expected = vec![
+            ("sub".to_string(), false),
+            ("top".to_string(), true),
+            (root_name, false),
+        ];
+        expected.sort();
+        assert_eq!(reported, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn followed_links_count_their_target_once() {
+        let tree = TempDir::new().unwrap();
+        fs::create_dir_all(tree.path().join("a/sub")).unwrap();
+        fs::write(tree.path().join("a/f"), vec![1u8; 64 * 1024]).unwrap();
+        fs::create_dir(tree.path().join("links")).unwrap();
+        std::os::unix::fs::symlink(tree.path().join("a/f"), tree.path().join("links/f")).unwrap();
+        std::os::unix::fs::symlink(tree.path().join("a"), tree.path().join("links/a")).unwrap();
+
+        let scan = |symlinks| {
+            let config = ScanConfig {
+                symlinks,
+                ..Default::default()
+            };
+            let core = ScannerCore::with_config(&config).unwrap();
+            core.scan_directory(tree.path()).unwrap()
+        };
+        let followed = scan(SymlinkPolicy::Follow);
+        let skipped = scan(SymlinkPolicy::Skip);
+        assert_eq!(followed.size, skipped.size);
+        assert!(followed.size >= 64 * 1024);
+    }
+
+    #[test]
+    fn paused_time_does_not_count_towards_the_timeout() {
+        let config = ScanConfig {
+            timeout_secs: Some(1),
+            ..Default::default()
+        };
+        let mut core = ScannerCore::with_config(&config).unwrap();
+        // Moving the epoch back has the scanner see that much more time pass
+        let advance = |core: &mut ScannerCore| core.epoch -= Duration::from_millis(1200);
+        core.reset();
+        core.pause();
+        advance(&mut core);
+        assert!(!core.should_stop());
+
+        core.resume();
+        assert!(!core.should_stop());
+        advance(&mut core);
+        assert!(core.should_stop());
+        assert!(core.limits_hit().timeout);
+    }
+
+    #[test]
+    fn paused_scan_does_not_block_the_shared_pool() {
+        let tree = disk_tree(4, 4);
+        let core = ScannerCore::new().with_own_pool().unwrap();
+        core.pause();
+        let scan = {
+            let core = core.clone();
+            let root = tree.path().to_path_buf();
+            std::thread::spawn(move || core.scan_subtree(&root, 0))
+        };
+
+        let other = tree.path().join("dir0");
+        let page = within(5, move || {
+            load_children(&other, &ChildrenOptions::default())
+        })
+        .expect("lazy load finished while the scan was paused")
+        .unwrap();
+        assert_eq!(page.total, 4);
+
+        core.resume();
+        assert_eq!(scan.join().unwrap().unwrap().item_count, 16);
+    }
 }
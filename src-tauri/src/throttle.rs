@@ -0,0 +1,60 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Paces filesystem operations across all scan threads to a maximum rate
+#[derive(Debug)]
+pub struct Throttle {
+    interval: Duration,
+    /// Earliest time the next operation may start
+    next_slot: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub fn new(ops_per_sec: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / ops_per_sec.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Block until the caller may perform one operation
+    pub fn acquire(&self) {
+        let wait = {
+            let mut next_slot = self.next_slot.lock().expect("Throttle lock poisoned");
+            let now = Instant::now();
+            // Idle time doesn't bank up into a burst
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + self.interval;
+            slot - now
+        };
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Move the calling thread to the lowest CPU priority and the idle I/O class,
+/// so it only uses the disk when nothing else is waiting on it
+#[cfg(target_os = "linux")]
+pub fn lower_current_thread_priority() {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    // SAFETY: plain syscalls on the current thread (tid 0); failures are ignored
+    // because running at normal priority is an acceptable fallback
+    unsafe {
+        // On Linux, PRIO_PROCESS with who = 0 applies to the calling thread only
+        libc::setpriority(libc::PRIO_PROCESS, 0, 19);
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn lower_current_thread_priority() {}
@@ -0,0 +1,83 @@
+//! Small helpers shared by the scan analysis modules
+
+/// Parse sizes like "500 MB" or "1.5GB" (binary units)
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size: {}", text))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "K" => 1 << 10,
+        "MB" | "M" => 1 << 20,
+        "GB" | "G" => 1 << 30,
+        "TB" | "T" => 1 << 40,
+        _ => return Err(format!("Invalid size unit: {}", unit.trim())),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Initial buffer for the reentrant passwd/group lookups, doubled on ERANGE
+#[cfg(unix)]
+const LOOKUP_BUFFER_SIZE: usize = 1024;
+#[cfg(unix)]
+const MAX_LOOKUP_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; LOOKUP_BUFFER_SIZE];
+    loop {
+        let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: entry and buf are valid for writes and buf.len() is its real length
+        let rc =
+            unsafe { libc::getpwuid_r(uid, &mut entry, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if rc == libc::ERANGE && buf.len() < MAX_LOOKUP_BUFFER_SIZE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if rc != 0 || result.is_null() {
+            return None;
+        }
+        // SAFETY: on success pw_name points to a NUL-terminated string inside buf
+        let name = unsafe { std::ffi::CStr::from_ptr(entry.pw_name) };
+        return Some(name.to_string_lossy().to_string());
+    }
+}
+
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; LOOKUP_BUFFER_SIZE];
+    loop {
+        let mut entry: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: entry and buf are valid for writes and buf.len() is its real length
+        let rc =
+            unsafe { libc::getgrgid_r(gid, &mut entry, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if rc == libc::ERANGE && buf.len() < MAX_LOOKUP_BUFFER_SIZE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if rc != 0 || result.is_null() {
+            return None;
+        }
+        // SAFETY: on success gr_name points to a NUL-terminated string inside buf
+        let name = unsafe { std::ffi::CStr::from_ptr(entry.gr_name) };
+        return Some(name.to_string_lossy().to_string());
+    }
+}
+
+#[cfg(not(unix))]
+pub fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn group_name(_gid: u32) -> Option<String> {
+    None
+}
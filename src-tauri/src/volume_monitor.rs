@@ -0,0 +1,143 @@
+use crate::alerts::{AlertManager, AlertState, VolumeAlert};
+use crate::volumes::{collect_volumes, shown_by_default, VolumeInfo};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use sysinfo::Disks;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+/// Default time between disk refreshes
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 2000;
+/// Lower bound so a misconfigured interval can't spin the poll loop
+pub const MIN_POLL_INTERVAL_MS: u64 = 250;
+/// Default change in available space that triggers an event
+pub const DEFAULT_CHANGE_THRESHOLD_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct MonitorConfig {
+    pub poll_interval: Duration,
+    pub change_threshold: u64,
+    /// List hidden mounts in `volumes:changed` too; alerts always cover every mount
+    pub include_all: bool,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(DEFAULT_POLL_INTERVAL_MS),
+            change_threshold: DEFAULT_CHANGE_THRESHOLD_BYTES,
+            include_all: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumesChanged {
+    pub volumes: Vec<VolumeInfo>,
+}
+
+/// Polls mounted volumes on a background thread and emits `volumes:changed`
+/// whenever available space moves by at least the configured threshold.
+/// Low-space alerts are evaluated against every mount on each poll and emitted
+/// as `volumes:alert`.
+pub struct VolumeMonitor {
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl VolumeMonitor {
+    pub fn start(app: AppHandle, config: MonitorConfig, alerts: Arc<Mutex<AlertManager>>) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let handle = thread::spawn(move || Self::run(app, config, alerts, thread_stopped));
+
+        Self {
+            stopped,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            // Wake the poll loop so it notices the stop flag immediately
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+
+    fn run(
+        app: AppHandle,
+        config: MonitorConfig,
+        alerts: Arc<Mutex<AlertManager>>,
+        stopped: Arc<AtomicBool>,
+    ) {
+        let mut disks = Disks::new();
+        // Available space per mount point as of the last emitted event
+        let mut baseline: HashMap<String, u64> = HashMap::new();
+
+        while !stopped.load(Ordering::SeqCst) {
+            // Re-list rather than refresh so mounts and unmounts are picked up too
+            disks.refresh_list();
+            let all = collect_volumes(&disks, true);
+
+            // Thresholds can be set on mounts the listing hides, like network shares
+            let crossed = alerts
+                .lock()
+                .expect("Alert manager lock poisoned")
+                .evaluate(&all);
+            for alert in crossed {
+                Self::emit_alert(&app, &alert);
+            }
+
+            let volumes = if config.include_all {
+                all
+            } else {
+                shown_by_default(all)
+            };
+
+            if has_changed(&baseline, &volumes, config.change_threshold) {
+                baseline = volumes
+                    .iter()
+                    .map(|v| (v.mount_point.clone(), v.available_space))
+                    .collect();
+                let _ = app.emit("volumes:changed", VolumesChanged { volumes });
+            }
+
+            thread::park_timeout(config.poll_interval);
+        }
+    }
+
+    fn emit_alert(app: &AppHandle, alert: &VolumeAlert) {
+        let _ = app.emit("volumes:alert", alert.clone());
+        if alert.state == AlertState::Triggered {
+            let _ = app
+                .notification()
+                .builder()
+                .title("Low disk space")
+                .body(alert.notification_body())
+                .show();
+        }
+    }
+}
+
+impl Drop for VolumeMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn has_changed(baseline: &HashMap<String, u64>, volumes: &[VolumeInfo], threshold: u64) -> bool {
+    if baseline.len() != volumes.len() {
+        return true;
+    }
+
+    volumes.iter().any(|v| match baseline.get(&v.mount_point) {
+        Some(&previous) => previous.abs_diff(v.available_space) >= threshold,
+        None => true,
+    })
+}
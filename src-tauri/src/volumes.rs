@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::path::Path;
 use sysinfo::Disks;
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -9,43 +11,542 @@ pub struct VolumeInfo {
     pub available_space: u64,
     pub file_system: String,
     pub is_removable: bool,
+    /// Percentage of total space in use, including space reserved for root
+    pub used_percent: f64,
+    pub total_inodes: Option<u64>,
+    pub free_inodes: Option<u64>,
+    /// Bytes free on disk but only usable by privileged users
+    pub reserved_space: Option<u64>,
+    /// Underlying device or mount source (e.g. /dev/sda1, server:/export)
+    pub device: String,
+    /// Per-mount options such as `ro` or `noexec`
+    pub mount_options: Vec<String>,
+    pub kind: VolumeKind,
+    /// Mounts a subdirectory of another filesystem (Linux bind mounts)
+    pub is_bind_mount: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VolumeKind {
+    Physical,
+    Network,
+    /// Memory-backed or kernel pseudo filesystems (tmpfs, proc, sysfs, ...)
+    Virtual,
+    /// Container storage layers (overlay, aufs)
+    Overlay,
+    /// Loop-mounted images such as snap packages
+    Loop,
+}
+
+impl VolumeKind {
+    /// Whether volumes of this kind are omitted unless everything is requested
+    fn hidden_by_default(self) -> bool {
+        matches!(self, Self::Virtual | Self::Overlay | Self::Loop)
+    }
+}
+
+const VIRTUAL_FILE_SYSTEMS: &[&str] = &[
+    "tmpfs",
+    "devtmpfs",
+    "ramfs",
+    "proc",
+    "sysfs",
+    "devpts",
+    "cgroup",
+    "cgroup2",
+    "securityfs",
+    "debugfs",
+    "tracefs",
+    "configfs",
+    "pstore",
+    "bpf",
+    "mqueue",
+    "hugetlbfs",
+    "autofs",
+    "binfmt_misc",
+    "efivarfs",
+    "fusectl",
+    "nsfs",
+    "rpc_pipefs",
+    "devfs",
+    "fuse.portal",
+    "fuse.gvfsd-fuse",
+];
+
+const NETWORK_FILE_SYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afpfs",
+    "webdav",
+    "davfs",
+    "9p",
+    "ceph",
+    "glusterfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+];
+
+const OVERLAY_FILE_SYSTEMS: &[&str] = &["overlay", "aufs", "fuse-overlayfs"];
+
+/// Raw counters from statvfs for a mount point
+#[derive(Debug, Clone, Copy)]
+struct FsStats {
+    total_inodes: u64,
+    free_inodes: u64,
+    reserved_space: u64,
+}
+
+/// A single entry from /proc/self/mountinfo
+#[derive(Debug, Clone)]
+struct MountEntry {
+    source: String,
+    /// `major:minor` of the mounted filesystem
+    device: String,
+    /// Path within the source filesystem that is mounted: a subdirectory for bind
+    /// mounts, and for btrfs subvolumes such as `/@home`
+    root: String,
+    options: Vec<String>,
+}
+
+/// List mounted volumes, hiding virtual, overlay and loop mounts and bind-mounted
+/// duplicates unless `include_all` is set
 #[tauri::command]
-pub async fn list_volumes() -> Result<Vec<VolumeInfo>, String> {
+pub async fn list_volumes(include_all: Option<bool>) -> Result<Vec<VolumeInfo>, String> {
     let disks = Disks::new_with_refreshed_list();
+    Ok(collect_volumes(&disks, include_all.unwrap_or(false)))
+}
 
-    let mut seen_names: std::collections::HashMap<String, String> =
-        std::collections::HashMap::new();
-    let volumes: Vec<VolumeInfo> = disks
-        .iter()
-        .filter_map(|disk| {
-            let mount_point = disk.mount_point().to_string_lossy().to_string();
-            let name = disk.name().to_string_lossy().to_string();
+/// Build volume details from an already refreshed sysinfo disk list
+pub fn collect_volumes(disks: &Disks, include_all: bool) -> Vec<VolumeInfo> {
+    let volumes = collect_all_volumes(disks);
+    if include_all {
+        volumes
+    } else {
+        shown_by_default(volumes)
+    }
+}
 
+/// Drop virtual, overlay and loop mounts, bind-mounted duplicates and other
+/// volumes that `list_volumes` hides unless asked for all of them
+pub fn shown_by_default(volumes: Vec<VolumeInfo>) -> Vec<VolumeInfo> {
+    let mut seen_names: HashMap<String, String> = HashMap::new();
+    volumes
+        .into_iter()
+        .filter(|volume| {
             // Skip macOS data volume if we have root mounted
-            if mount_point.starts_with("/System/Volumes/Data") {
-                return None;
+            if volume.mount_point.starts_with("/System/Volumes/Data") {
+                return false;
+            }
+
+            if volume.kind.hidden_by_default() || volume.is_bind_mount {
+                return false;
             }
 
             // Skip duplicates based on disk name, preferring shorter mount points
-            if let Some(existing_mount) = seen_names.get(&name) {
-                if mount_point.len() >= existing_mount.len() {
-                    return None;
+            if let Some(existing_mount) = seen_names.get(&volume.name) {
+                if volume.mount_point.len() >= existing_mount.len() {
+                    return false;
                 }
             }
-            seen_names.insert(name.clone(), mount_point.clone());
+            seen_names.insert(volume.name.clone(), volume.mount_point.clone());
+            true
+        })
+        .collect()
+}
+
+fn collect_all_volumes(disks: &Disks) -> Vec<VolumeInfo> {
+    let mount_table = read_mount_table();
+
+    disks
+        .iter()
+        .map(|disk| {
+            let mount_point = disk.mount_point().to_string_lossy().to_string();
+            let name = disk.name().to_string_lossy().to_string();
+
+            let file_system = disk.file_system().to_string_lossy().to_string();
+            let mount = mount_table.get(&mount_point);
+            let device = mount.map_or_else(|| name.clone(), |m| m.source.clone());
+            let kind = classify_volume(&file_system, &device, &mount_point);
+            let is_bind_mount = mount.is_some_and(|m| is_bind_duplicate(m, &mount_table));
+
+            let total_space = disk.total_space();
+            let available_space = disk.available_space();
+            let stats = if stats_may_block(kind, &file_system) {
+                None
+            } else {
+                fs_stats(disk.mount_point())
+            };
 
-            Some(VolumeInfo {
+            // Reserved blocks count as used: they're unavailable to regular users
+            let used_percent = if total_space > 0 {
+                total_space.saturating_sub(available_space) as f64 / total_space as f64 * 100.0
+            } else {
+                0.0
+            };
+
+            VolumeInfo {
+                device,
+                mount_options: mount.map(|m| m.options.clone()).unwrap_or_default(),
                 name,
                 mount_point,
-                total_space: disk.total_space(),
-                available_space: disk.available_space(),
-                file_system: disk.file_system().to_string_lossy().to_string(),
+                total_space,
+                available_space,
+                file_system,
                 is_removable: disk.is_removable(),
-            })
+                used_percent,
+                total_inodes: stats.map(|s| s.total_inodes),
+                free_inodes: stats.map(|s| s.free_inodes),
+                reserved_space: stats.map(|s| s.reserved_space),
+                kind,
+                is_bind_mount,
+            }
         })
-        .collect();
+        .collect()
+}
+
+/// Whether another mount of the same filesystem exposes a directory above this
+/// one's root, making this a bind-mounted view into it
+fn is_bind_duplicate(mount: &MountEntry, mount_table: &HashMap<String, MountEntry>) -> bool {
+    let root = Path::new(&mount.root);
+    mount_table.values().any(|other| {
+        other.device == mount.device && other.root != mount.root && root.starts_with(&other.root)
+    })
+}
+
+/// Bytes in use on the volume mounted exactly at `path`, if it is a mount point.
+/// Only that mount is stat'ed, so a dead network mount elsewhere can't hang it.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs field widths differ between platforms
+pub fn used_space_at(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    // A mount point is on a different device than its parent, except for `/`
+    let device = std::fs::metadata(path).ok()?.dev();
+    if let Some(parent) = path.parent() {
+        if std::fs::metadata(parent).ok()?.dev() == device {
+            return None;
+        }
+    }
+
+    // Reserved blocks are free, just not to regular users
+    let stat = statvfs(path)?;
+    Some((stat.f_blocks as u64).saturating_sub(stat.f_bfree as u64) * stat.f_frsize as u64)
+}
+
+/// Bytes in use on the volume mounted exactly at `path`, if it is a mount point.
+/// Only that volume is queried, so a dead network share elsewhere can't hang it.
+#[cfg(windows)]
+pub fn used_space_at(path: &Path) -> Option<u64> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use windows_sys::Win32::Storage::FileSystem::{GetDiskFreeSpaceExW, GetVolumePathNameW};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut volume = [0u16; 261];
+    // SAFETY: wide is NUL-terminated and volume is writable for its whole length
+    if unsafe { GetVolumePathNameW(wide.as_ptr(), volume.as_mut_ptr(), volume.len() as u32) } == 0 {
+        return None;
+    }
+    let len = volume.iter().position(|&c| c == 0)?;
+    // Comparing paths ignores the volume path's trailing backslash
+    if Path::new(&OsString::from_wide(&volume[..len])) != path {
+        return None;
+    }
+
+    let (mut total, mut free) = (0u64, 0u64);
+    // SAFETY: wide is NUL-terminated and both out pointers are valid u64s
+    let ok =
+        unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), std::ptr::null_mut(), &mut total, &mut free) };
+    (ok != 0).then(|| total.saturating_sub(free))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn used_space_at(_path: &Path) -> Option<u64> {
+    None
+}
+
+fn classify_volume(file_system: &str, device: &str, mount_point: &str) -> VolumeKind {
+    let file_system = file_system.to_lowercase();
+    let is_one_of = |list: &[&str]| list.contains(&file_system.as_str());
+
+    if is_one_of(OVERLAY_FILE_SYSTEMS) {
+        VolumeKind::Overlay
+    } else if is_one_of(NETWORK_FILE_SYSTEMS) || device.starts_with("//") {
+        VolumeKind::Network
+    } else if file_system == "squashfs"
+        || device.starts_with("/dev/loop")
+        || mount_point.starts_with("/snap/")
+    {
+        VolumeKind::Loop
+    } else if is_one_of(VIRTUAL_FILE_SYSTEMS)
+        // macOS system volumes (VM, Preboot, Update, ...) other than the Data volume
+        || (mount_point.starts_with("/System/Volumes/")
+            && !mount_point.starts_with("/System/Volumes/Data"))
+    {
+        VolumeKind::Virtual
+    } else {
+        VolumeKind::Physical
+    }
+}
+
+/// Whether statvfs can hang on the mount: an unreachable server or a stuck FUSE
+/// daemon would stall the whole listing, so their inode details are left out
+fn stats_may_block(kind: VolumeKind, file_system: &str) -> bool {
+    kind == VolumeKind::Network || file_system.to_lowercase().starts_with("fuse")
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs field widths differ between platforms
+fn fs_stats(mount_point: &Path) -> Option<FsStats> {
+    let stat = statvfs(mount_point)?;
+    let fragment_size = stat.f_frsize as u64;
+    Some(FsStats {
+        total_inodes: stat.f_files as u64,
+        free_inodes: stat.f_ffree as u64,
+        reserved_space: (stat.f_bfree as u64).saturating_sub(stat.f_bavail as u64) * fragment_size,
+    })
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Option<libc::statvfs> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is NUL-terminated and stat is a valid, writable statvfs
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat)
+}
+
+#[cfg(not(unix))]
+fn fs_stats(_mount_point: &Path) -> Option<FsStats> {
+    None
+}
+
+/// Map mount points to their source device and options
+#[cfg(target_os = "linux")]
+fn read_mount_table() -> HashMap<String, MountEntry> {
+    std::fs::read_to_string("/proc/self/mountinfo")
+        .map(|content| content.lines().filter_map(parse_mountinfo_line).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_mount_table() -> HashMap<String, MountEntry> {
+    HashMap::new()
+}
+
+/// Parse a mountinfo line:
+/// `id parent major:minor root mount_point options [optional...] - fstype source super_options`
+#[cfg(target_os = "linux")]
+fn parse_mountinfo_line(line: &str) -> Option<(String, MountEntry)> {
+    let (mount_fields, fs_fields) = line.split_once(" - ")?;
+    let mut mount_fields = mount_fields.split(' ');
+    let device = mount_fields.nth(2)?;
+    let root = mount_fields.next()?;
+    let mount_point = mount_fields.next()?;
+    let options = mount_fields.next()?;
+    let source = fs_fields.split(' ').nth(1)?;
+
+    Some((
+        unescape_mountinfo(mount_point),
+        MountEntry {
+            source: unescape_mountinfo(source),
+            device: device.to_string(),
+            root: unescape_mountinfo(root),
+            options: options.split(',').map(str::to_string).collect(),
+        },
+    ))
+}
+
+/// Decode the octal escapes (`\040` for space etc.) used in mountinfo fields
+#[cfg(target_os = "linux")]
+fn unescape_mountinfo(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(value) = u8::from_str_radix(digits, 8) {
+                out.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mount(device: &str, root: &str) -> MountEntry {
+        MountEntry {
+            source: "/dev/sda2".to_string(),
+            device: device.to_string(),
+            root: root.to_string(),
+            options: vec![],
+        }
+    }
+
+    fn table(mounts: &[(&str, MountEntry)]) -> HashMap<String, MountEntry> {
+        mounts
+            .iter()
+            .map(|(mount_point, entry)| (mount_point.to_string(), entry.clone()))
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn used_space_is_only_reported_for_mount_points() {
+        assert!(used_space_at(Path::new("/")).is_some_and(|used| used > 0));
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(used_space_at(dir.path()), None);
+    }
+
+    fn volume(name: &str, mount_point: &str, kind: VolumeKind, is_bind_mount: bool) -> VolumeInfo {
+        VolumeInfo {
+            name: name.to_string(),
+            mount_point: mount_point.to_string(),
+            total_space: 100,
+            available_space: 50,
+            file_system: "ext4".to_string(),
+            is_removable: false,
+            used_percent: 50.0,
+            total_inodes: None,
+            free_inodes: None,
+            reserved_space: None,
+            device: name.to_string(),
+            mount_options: vec![],
+            kind,
+            is_bind_mount,
+        }
+    }
+
+    #[test]
+    fn hidden_mounts_are_dropped_from_the_default_listing() {
+        let all = vec![
+            volume("/dev/sda2", "/", VolumeKind::Physical, false),
+            volume("tmpfs", "/tmp", VolumeKind::Virtual, false),
+            volume("/dev/sda2", "/srv/bound", VolumeKind::Physical, true),
+            volume("nas:/export", "/mnt/nas", VolumeKind::Network, false),
+            volume("/dev/sdb1", "/mnt/data", VolumeKind::Physical, false),
+            volume("/dev/sdb1", "/mnt/data/again", VolumeKind::Physical, false),
+        ];
+
+        let shown: Vec<_> = shown_by_default(all)
+            .into_iter()
+            .map(|v| v.mount_point)
+            .collect();
+        assert_eq!(shown, vec!["/", "/mnt/nas", "/mnt/data"]);
+    }
+
+    #[test]
+    fn network_and_fuse_mounts_are_not_statted() {
+        let stats_may_block = |file_system: &str, device: &str| {
+            let kind = classify_volume(file_system, device, "/mnt/x");
+            stats_may_block(kind, file_system)
+        };
+        assert!(stats_may_block("nfs4", "nas:/export"));
+        assert!(stats_may_block("cifs", "//nas/share"));
+        assert!(stats_may_block("fuse.sshfs", "user@host:"));
+        assert!(stats_may_block("fuse.mergerfs", "pool"));
+        assert!(stats_may_block("fuseblk", "/dev/sdb1"));
+        assert!(!stats_may_block("ext4", "/dev/sda2"));
+        assert!(!stats_may_block("apfs", "/dev/disk3s1"));
+    }
+
+    #[test]
+    fn btrfs_subvolumes_are_not_bind_mounts() {
+        let mounts = table(&[
+            ("/", mount("0:31", "/@")),
+            ("/home", mount("0:31", "/@home")),
+        ]);
+        assert!(!is_bind_duplicate(&mounts["/"], &mounts));
+        assert!(!is_bind_duplicate(&mounts["/home"], &mounts));
+
+        // Fedora mounts the root subvolume as `/root`
+        let mounts = table(&[
+            ("/", mount("0:35", "/root")),
+            ("/home", mount("0:35", "/home")),
+        ]);
+        assert!(!is_bind_duplicate(&mounts["/"], &mounts));
+    }
+
+    #[test]
+    fn bind_mount_below_another_mount_is_a_duplicate() {
+        let mounts = table(&[
+            ("/", mount("8:2", "/")),
+            ("/srv/data", mount("8:2", "/var/lib/data")),
+            ("/mnt/other", mount("8:17", "/var/lib/data")),
+        ]);
+        assert!(!is_bind_duplicate(&mounts["/"], &mounts));
+        assert!(is_bind_duplicate(&mounts["/srv/data"], &mounts));
+        // Same root, different filesystem
+        assert!(!is_bind_duplicate(&mounts["/mnt/other"], &mounts));
+    }
+
+    #[test]
+    fn subdirectory_of_a_subvolume_is_a_duplicate() {
+        let mounts = table(&[
+            ("/home", mount("0:31", "/@home")),
+            ("/srv/alice", mount("0:31", "/@home/alice")),
+            ("/srv/prefix", mount("0:31", "/@home2")),
+        ]);
+        assert!(is_bind_duplicate(&mounts["/srv/alice"], &mounts));
+        // Ancestry is by path component, not string prefix
+        assert!(!is_bind_duplicate(&mounts["/srv/prefix"], &mounts));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_mountinfo_lines() {
+        let line = "36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue";
+        let (mount_point, entry) = parse_mountinfo_line(line).unwrap();
+        assert_eq!(mount_point, "/mnt2");
+        assert_eq!(entry.device, "98:0");
+        assert_eq!(entry.root, "/mnt1");
+        assert_eq!(entry.source, "/dev/root");
+        assert_eq!(entry.options, ["rw", "noatime"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_mountinfo_lines_without_optional_fields() {
+        let line = "25 1 0:22 / /mnt/My\\040Drive rw - nfs4 server:/export\\040x rw,vers=4.2";
+        let (mount_point, entry) = parse_mountinfo_line(line).unwrap();
+        assert_eq!(mount_point, "/mnt/My Drive");
+        assert_eq!(entry.source, "server:/export x");
+        assert_eq!(entry.root, "/");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rejects_malformed_mountinfo_lines() {
+        assert!(parse_mountinfo_line("").is_none());
+        assert!(parse_mountinfo_line("36 35 98:0 /mnt1 /mnt2 rw").is_none());
+    }
 
-    Ok(volumes)
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unescapes_octal_sequences() {
+        assert_eq!(unescape_mountinfo("a\\040b"), "a b");
+        assert_eq!(unescape_mountinfo("tab\\011"), "tab\t");
+        assert_eq!(unescape_mountinfo("back\\134slash"), "back\\slash");
+        // Not an escape: too short or not octal
+        assert_eq!(unescape_mountinfo("end\\04"), "end\\04");
+        assert_eq!(unescape_mountinfo("x\\999"), "x\\999");
+        assert_eq!(unescape_mountinfo("plain"), "plain");
+    }
 }
@@ -0,0 +1,466 @@
+use crate::name_index::NameIndex;
+use crate::scanner::{DirNode, ScannerCore, LAZY_LOAD_DEPTH};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// How long to keep collecting events after the first one before applying a batch
+const BATCH_WINDOW_MS: u64 = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeUpdate {
+    pub path: String,
+    pub size: u64,
+    pub item_count: u64,
+    pub stale_size: u64,
+    pub mtime: Option<u64>,
+}
+
+/// Coalesced changes from one batch of filesystem events
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanUpdated {
+    /// Created or changed nodes, truncated like lazy-loaded children
+    pub upserted: Vec<DirNode>,
+    pub removed: Vec<String>,
+    /// New totals for every ancestor affected by the batch
+    pub sizes: Vec<SizeUpdate>,
+    /// Replacement for the whole tree after a full rescan, when events were dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<DirNode>,
+}
+
+/// Watches a scanned root and keeps the stored tree in sync, emitting `scan:updated`
+pub struct ScanWatcher {
+    // Dropping the watcher closes the event channel, which ends the batching thread
+    watcher: Option<RecommendedWatcher>,
+    handle: Option<JoinHandle<()>>,
+    /// Shares its cancellation with the batching thread's scanner
+    scanner: ScannerCore,
+}
+
+impl ScanWatcher {
+    /// `scanner` rescans changed paths with the scan's settings and hard-link
+    /// records (see `ScannerCore::fork`). `name_index` is cleared whenever the
+    /// tree changes so it's rebuilt on next use.
+    pub fn start(
+        app: AppHandle,
+        tree: Arc<RwLock<Option<DirNode>>>,
+        name_index: Arc<RwLock<Option<NameIndex>>>,
+        scanner: ScannerCore,
+    ) -> Result<Self, String> {
+        let root = tree
+            .read()
+            .expect("Scan result lock poisoned")
+            .as_ref()
+            .map(|root| PathBuf::from(&root.path))
+            .ok_or("No completed scan to watch")?;
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(tx).map_err(|e| format!("Cannot watch: {}", e))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Cannot watch {}: {}", root.display(), e))?;
+
+        let thread_scanner = scanner.clone();
+        let handle =
+            thread::spawn(move || Self::run(app, tree, name_index, thread_scanner, root, rx));
+
+        Ok(Self {
+            watcher: Some(watcher),
+            handle: Some(handle),
+            scanner,
+        })
+    }
+
+    /// Stop watching, cutting short a rescan in progress. Still blocks until the
+    /// batching thread notices, so don't call it on the async runtime.
+    pub fn stop(&mut self) {
+        self.scanner.cancel();
+        self.watcher.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn run(
+        app: AppHandle,
+        tree: Arc<RwLock<Option<DirNode>>>,
+        name_index: Arc<RwLock<Option<NameIndex>>>,
+        scanner: ScannerCore,
+        root: PathBuf,
+        rx: Receiver<notify::Result<notify::Event>>,
+    ) {
+        while let Some(batch) = next_batch(&rx) {
+            // Scanning happens without the tree lock so commands aren't held up
+            let update = if batch.rescan_root {
+                let Ok(new_root) = scanner.rescan_directory(&root) else {
+                    continue;
+                };
+                // A stopped watcher's rescan is cut short and must not be stored
+                if scanner.is_cancelled() {
+                    return;
+                }
+                let truncated = new_root.truncated(LAZY_LOAD_DEPTH);
+                *tree.write().expect("Scan result lock poisoned") = Some(new_root);
+                Some(ScanUpdated {
+                    upserted: Vec::new(),
+                    removed: Vec::new(),
+                    sizes: Vec::new(),
+                    root: Some(truncated),
+                })
+            } else {
+                let changes = match tree.read().expect("Scan result lock poisoned").as_ref() {
+                    Some(current) => plan_changes(current, batch.paths),
+                    None => return,
+                };
+                let scanned = scan_changes(&scanner, &root, changes);
+                if scanner.is_cancelled() {
+                    return;
+                }
+                let mut tree_lock = tree.write().expect("Scan result lock poisoned");
+                let Some(current) = tree_lock.as_mut() else {
+                    return;
+                };
+                apply_changes(current, scanned)
+            };
+
+            if let Some(update) = update {
+                *name_index.write().expect("Name index lock poisoned") = None;
+                let _ = app.emit("scan:updated", update);
+            }
+        }
+    }
+}
+
+impl Drop for ScanWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Paths touched by one batch of filesystem events
+#[derive(Debug, Default)]
+struct Batch {
+    paths: HashSet<PathBuf>,
+    /// The event queue overflowed, so only a full rescan can bring the tree back in sync
+    rescan_root: bool,
+}
+
+/// Block for the next event, then gather everything arriving within the batch
+/// window. `None` once the watcher is gone.
+fn next_batch(rx: &Receiver<notify::Result<notify::Event>>) -> Option<Batch> {
+    let mut batch = Batch::default();
+    collect_event(rx.recv().ok()?, &mut batch);
+
+    let deadline = Instant::now() + Duration::from_millis(BATCH_WINDOW_MS);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining) {
+            Ok(event) => collect_event(event, &mut batch),
+            Err(RecvTimeoutError::Timeout) => return Some(batch),
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+fn collect_event(event: notify::Result<notify::Event>, batch: &mut Batch) {
+    match event {
+        Ok(event) if event.need_rescan() => batch.rescan_root = true,
+        // Access events don't change sizes
+        Ok(event) if !event.kind.is_access() => batch.paths.extend(event.paths),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Change {
+    Rescan(PathBuf),
+    Remove(PathBuf),
+}
+
+/// Work out what each changed path below the root needs, parents first
+fn plan_changes(root: &DirNode, paths: HashSet<PathBuf>) -> Vec<Change> {
+    let root_path = PathBuf::from(&root.path);
+    let mut paths: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|p| p != &root_path && p.starts_with(&root_path))
+        .collect();
+    paths.sort_by(|a, b| (a.components().count(), a).cmp(&(b.components().count(), b)));
+
+    let mut changes = Vec::new();
+    // A rescanned or removed path covers every event below it
+    let mut covered = HashSet::new();
+    for path in paths {
+        if path.ancestors().skip(1).any(|a| covered.contains(a)) {
+            continue;
+        }
+        let existing = root.find(&path);
+        let change = if path.symlink_metadata().is_ok() {
+            // Existing directories only need their children's events; rescanning
+            // them would redo the whole subtree for every entry change inside
+            if path.is_dir() && existing.is_some_and(|n| !n.is_file) {
+                continue;
+            }
+            Change::Rescan(path.clone())
+        } else if existing.is_some() {
+            Change::Remove(path.clone())
+        } else {
+            continue;
+        };
+        covered.insert(path);
+        changes.push(change);
+    }
+    changes
+}
+
+/// Scan the paths that need it, pairing each change with its new node (`None` to remove)
+fn scan_changes(
+    scanner: &ScannerCore,
+    root: &Path,
+    changes: Vec<Change>,
+) -> Vec<(PathBuf, Option<DirNode>)> {
+    changes
+        .into_iter()
+        .filter_map(|change| match change {
+            Change::Rescan(path) => {
+                scanner.forget_under(&path);
+                let depth = path
+                    .strip_prefix(root)
+                    .map_or(0, |relative| relative.components().count());
+                let node = scanner.scan_subtree(&path, depth).ok()?;
+                Some((path, Some(node)))
+            }
+            Change::Remove(path) => {
+                scanner.forget_under(&path);
+                Some((path, None))
+            }
+        })
+        .collect()
+}
+
+/// Splice scanned nodes into the tree, returning the coalesced delta
+fn apply_changes(
+    root: &mut DirNode,
+    changes: Vec<(PathBuf, Option<DirNode>)>,
+) -> Option<ScanUpdated> {
+    let mut upserted = Vec::new();
+    let mut removed = Vec::new();
+    let mut sizes = BTreeMap::new();
+
+    for (path, node) in changes {
+        let truncated = node.as_ref().map(|n| n.truncated(LAZY_LOAD_DEPTH));
+        let applied = root.replace_descendant(&path, node, &mut |ancestor| {
+            sizes.insert(
+                ancestor.path.clone(),
+                SizeUpdate {
+                    path: ancestor.path.clone(),
+                    size: ancestor.size,
+                    item_count: ancestor.item_count,
+                    stale_size: ancestor.stale_size,
+                    mtime: ancestor.mtime,
+                },
+            );
+        });
+        if !applied {
+            continue;
+        }
+
+        match truncated {
+            Some(node) => upserted.push(node),
+            None => removed.push(path.to_string_lossy().to_string()),
+        }
+    }
+
+    if upserted.is_empty() && removed.is_empty() {
+        return None;
+    }
+
+    Some(ScanUpdated {
+        upserted,
+        removed,
+        sizes: sizes.into_values().collect(),
+        root: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{ScanConfig, SymlinkPolicy};
+    use notify::event::{AccessKind, CreateKind, EventKind, Flag, ModifyKind};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn event(kind: EventKind, paths: &[PathBuf]) -> notify::Result<notify::Event> {
+        Ok(paths.iter().fold(notify::Event::new(kind), |event, path| {
+            event.add_path(path.clone())
+        }))
+    }
+
+    fn write(root: &Path, relative: &str, kib: usize) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![1u8; kib * 1024]).unwrap();
+    }
+
+    /// Temporary tree holding `files`, its scan and the scanner that made it
+    fn scanned(files: &[&str]) -> (TempDir, ScannerCore, DirNode) {
+        let dir = TempDir::new().unwrap();
+        for file in files {
+            write(dir.path(), file, 4);
+        }
+        let scanner = ScannerCore::new();
+        let root = scanner.scan_directory(dir.path()).unwrap();
+        (dir, scanner, root)
+    }
+
+    /// Plan, scan and apply `paths` the way one watcher batch does
+    fn update(scanner: &ScannerCore, root: &mut DirNode, paths: &[PathBuf]) -> Option<ScanUpdated> {
+        let changes = plan_changes(root, paths.iter().cloned().collect());
+        let scanned = scan_changes(scanner, &PathBuf::from(&root.path), changes);
+        apply_changes(root, scanned)
+    }
+
+    #[test]
+    fn events_within_the_window_form_one_batch() {
+        let (tx, rx) = mpsc::channel();
+        let path = |p: &str| [PathBuf::from(p)];
+        tx.send(event(EventKind::Create(CreateKind::File), &path("/r/a")))
+            .unwrap();
+        tx.send(event(EventKind::Access(AccessKind::Any), &path("/r/b")))
+            .unwrap();
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(BATCH_WINDOW_MS / 5));
+            tx.send(event(EventKind::Modify(ModifyKind::Any), &path("/r/c")))
+                .unwrap();
+            thread::sleep(Duration::from_millis(BATCH_WINDOW_MS * 3));
+        });
+
+        let batch = next_batch(&rx).unwrap();
+        let mut paths: Vec<_> = batch.paths.into_iter().collect();
+        paths.sort();
+        // Access events are left out
+        assert_eq!(paths, ["/r/a", "/r/c"].map(PathBuf::from));
+        assert!(!batch.rescan_root);
+
+        sender.join().unwrap();
+        assert!(next_batch(&rx).is_none());
+    }
+
+    #[test]
+    fn dropped_events_ask_for_a_full_rescan() {
+        let mut batch = Batch::default();
+        let overflow = notify::Event::new(EventKind::Other).set_flag(Flag::Rescan);
+        collect_event(Ok(overflow), &mut batch);
+        assert!(batch.rescan_root);
+    }
+
+    #[test]
+    fn plans_one_change_per_new_or_removed_subtree() {
+        let (dir, _, root) = scanned(&["a/f", "b/g"]);
+        let path = |relative: &str| dir.path().join(relative);
+        write(dir.path(), "new/x", 4);
+        write(dir.path(), "new/deep/y", 4);
+        fs::remove_dir_all(path("b")).unwrap();
+
+        let events = [
+            path("new"),
+            path("new/x"),
+            path("new/deep/y"),
+            path("b"),
+            path("b/g"),
+            // Existing directory, a path that never made it into the tree, the
+            // root itself and a path outside it need nothing
+            path("a"),
+            path("gone"),
+            dir.path().to_path_buf(),
+            PathBuf::from("/elsewhere"),
+        ];
+        assert_eq!(
+            plan_changes(&root, events.into_iter().collect()),
+            [Change::Remove(path("b")), Change::Rescan(path("new"))]
+        );
+    }
+
+    #[test]
+    fn updates_ancestor_totals_and_reports_the_delta() {
+        let (dir, scanner, mut root) = scanned(&["a/f", "a/b/g", "c/h"]);
+        let path = |relative: &str| dir.path().join(relative);
+        write(dir.path(), "a/b/new", 8);
+        fs::remove_file(path("c/h")).unwrap();
+
+        let update = update(&scanner, &mut root, &[path("a/b/new"), path("c/h")]).unwrap();
+
+        let upserted: Vec<&str> = update.upserted.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(upserted, [path("a/b/new").to_str().unwrap()]);
+        assert_eq!(update.removed, [path("c/h").to_string_lossy()]);
+        let mut updated: Vec<String> = update.sizes.iter().map(|s| s.path.clone()).collect();
+        updated.sort();
+        let mut ancestors = [dir.path().to_path_buf(), path("a"), path("a/b"), path("c")]
+            .map(|p| p.to_string_lossy().to_string());
+        ancestors.sort();
+        assert_eq!(updated, ancestors);
+        assert!(update.root.is_none());
+
+        // The patched tree matches a fresh scan, and so do the reported totals
+        let fresh = ScannerCore::new().scan_directory(dir.path()).unwrap();
+        assert_eq!(root.size, fresh.size);
+        assert_eq!(root.item_count, fresh.item_count);
+        for size in &update.sizes {
+            let node = fresh.find(Path::new(&size.path)).unwrap();
+            assert_eq!((size.size, size.item_count), (node.size, node.item_count));
+        }
+    }
+
+    #[test]
+    fn unchanged_batches_report_nothing() {
+        let (dir, scanner, mut root) = scanned(&["a/f"]);
+        let events = [dir.path().join("a"), dir.path().join("missing")];
+        assert!(update(&scanner, &mut root, &events).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rescans_apply_the_scans_symlink_policy() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "a/f", 4);
+        let config = ScanConfig {
+            symlinks: SymlinkPolicy::Skip,
+            ..Default::default()
+        };
+        let scanner = ScannerCore::with_config(&config).unwrap();
+        let mut root = scanner.scan_directory(dir.path()).unwrap();
+
+        write(dir.path(), "new/g", 4);
+        std::os::unix::fs::symlink(dir.path().join("a"), dir.path().join("new/link")).unwrap();
+        update(&scanner.fork(), &mut root, &[dir.path().join("new")]).unwrap();
+
+        let new = root.find(&dir.path().join("new")).unwrap();
+        let names: Vec<&str> = new.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["g"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rescans_keep_the_scans_hard_link_records() {
+        let (dir, scanner, _) = scanned(&["a/f"]);
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::hard_link(dir.path().join("a/f"), dir.path().join("b/f")).unwrap();
+        let mut root = scanner.scan_directory(dir.path()).unwrap();
+        let size = root.size;
+
+        // Either link may be the counted one; rescanning both keeps one copy
+        let watcher_scanner = scanner.fork();
+        let events = [dir.path().join("a/f"), dir.path().join("b/f")];
+        update(&watcher_scanner, &mut root, &events[..1]);
+        update(&watcher_scanner, &mut root, &events[1..]);
+        assert_eq!(root.size, size);
+    }
+}
@@ -0,0 +1,91 @@
+import { writable } from 'svelte/store';
+import { invoke } from '@tauri-apps/api/core';
+import type { FromScan } from './scan';
+
+// User-defined rules can name their own categories
+export type CleanupCategory =
+  | 'dependencies'
+  | 'build_output'
+  | 'cache'
+  | 'ide_data'
+  | (string & {});
+
+export interface RuleError {
+  rule_index: number | null;
+  rule_name: string | null;
+  line: number | null;
+  message: string;
+}
+
+export interface RuleValidation {
+  path: string;
+  exists: boolean;
+  rule_count: number;
+  errors: RuleError[];
+}
+
+export interface RuleTestReport extends FromScan {
+  rules: RuleMatches[];
+}
+
+export interface RuleMatches {
+  rule: string;
+  is_builtin: boolean;
+  matches: CleanupCandidate[];
+  total_size: number;
+}
+
+export interface CleanupCandidate {
+  name: string;
+  path: string;
+  size: number;
+  item_count: number;
+  rule: string;
+  category: CleanupCategory;
+  suggested_action: string;
+}
+
+export interface CleanupReport extends FromScan {
+  candidates: CleanupCandidate[];
+  categories: { category: CleanupCategory; size: number; count: number }[];
+  total_reclaimable: number;
+  rule_errors?: RuleError[];
+}
+
+interface CleanupState {
+  report: CleanupReport | null;
+  loading: boolean;
+  error: string | null;
+}
+
+function createCleanupStore() {
+  const { subscribe, set, update } = writable<CleanupState>({
+    report: null,
+    loading: false,
+    error: null
+  });
+
+  return {
+    subscribe,
+    async refresh() {
+      update((state) => ({ ...state, loading: true, error: null }));
+      try {
+        const report = await invoke<CleanupReport>('cleanup_suggestions');
+        set({ report, loading: false, error: null });
+      } catch (err) {
+        set({ report: null, loading: false, error: String(err) });
+      }
+    },
+    clear: () => set({ report: null, loading: false, error: null })
+  };
+}
+
+export function validateCleanupRules(path?: string): Promise<RuleValidation> {
+  return invoke<RuleValidation>('validate_cleanup_rules', { path: path ?? null });
+}
+
+export function testCleanupRules(path?: string): Promise<RuleTestReport> {
+  return invoke<RuleTestReport>('test_cleanup_rules', { path: path ?? null });
+}
+
+export const cleanupStore = createCleanupStore();
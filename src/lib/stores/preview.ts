@@ -0,0 +1,42 @@
+import { writable } from 'svelte/store';
+import { invoke } from '@tauri-apps/api/core';
+
+export interface ArchiveEntry {
+  path: string;
+  size: number;
+  is_dir: boolean;
+}
+
+export type PreviewContent =
+  | { kind: 'text'; encoding: string; text: string; truncated: boolean }
+  | { kind: 'image'; width: number; height: number; thumbnail: string | null }
+  | {
+      kind: 'archive';
+      format: string;
+      entries: ArchiveEntry[];
+      total_entries: number;
+      /** The archive wasn't read to the end, so it holds at least total_entries */
+      total_is_lower_bound: boolean;
+      truncated: boolean;
+    }
+  | {
+      kind: 'media';
+      duration_secs: number | null;
+      sample_rate: number | null;
+      channels: number | null;
+    }
+  | { kind: 'binary' };
+
+export type FilePreview = {
+  name: string;
+  path: string;
+  size: number;
+  mime: string;
+} & PreviewContent;
+
+export const activePreview = writable<FilePreview | null>(null);
+
+export async function openPreview(path: string): Promise<void> {
+  const preview = await invoke<FilePreview>('file_preview', { path });
+  activePreview.set(preview);
+}
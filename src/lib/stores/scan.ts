@@ -1,4 +1,4 @@
-import { writable } from 'svelte/store';
+import { get, writable } from 'svelte/store';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { invoke } from '@tauri-apps/api/core';
 
@@ -11,6 +11,23 @@ export interface DirNode {
   is_file: boolean;
   /** True if this directory has children that weren't loaded yet (lazy loading) */
   has_children?: boolean;
+  /** Newest modification time in the subtree (seconds since the epoch) */
+  mtime?: number;
+  atime?: number;
+  /** Bytes in files not modified since the stale cutoff */
+  stale_size?: number;
+  uid?: number;
+  gid?: number;
+  /** Placeholder for a directory that is still being scanned (partial results only) */
+  pending?: boolean;
+  /** Directory whose scan was cancelled or hit a limit before all of its entries were read */
+  incomplete?: boolean;
+  /** 'summary' for a synthetic node whose path exists on no filesystem */
+  kind?: 'entry' | 'summary';
+  /** Synthetic "N smaller items" node standing in for this many merged entries */
+  aggregated_count?: number;
+  /** Where this entry points, if it is a symlink */
+  symlink_target?: string;
   updatedAt?: number;
   seq?: number;
 }
@@ -23,36 +40,174 @@ export interface ScanHistoryEntry {
 
 interface ScanState {
   scanning: boolean;
+  /** Workers are held at directory boundaries until resumed */
+  paused: boolean;
   data: DirNode | null;
+  /** Tree assembled from scan:subtrees events while a scan is running */
+  partial: DirNode | null;
   totalScanned: number;
   currentPath: string;
+  /** Latest progress event of the running scan */
+  progress: ScanProgressEvent | null;
+  /** Limits that cut the last scan short */
+  limitsHit: ScanLimitsHit | null;
+  /** Options of the last scan, reused when loading children */
+  config: ScanConfig | null;
   error: string | null;
+  /** Backend is watching the scanned root and streaming scan:updated deltas */
+  watching: boolean;
   // Keep history alongside the active scan state so updates stay atomic when scans finish or reset.
   history: ScanHistoryEntry[];
 }
 
-type ScanProgressEvent = {
+export type ScanProgressEvent = {
   path: string;
   total_scanned: number;
+  bytes_scanned: number;
+  dirs_scanned: number;
+  error_count: number;
+  elapsed_ms: number;
+  files_per_sec: number;
+  bytes_per_sec: number;
+  /** Used space of the volume, when scanning a whole volume */
+  expected_bytes: number | null;
+  eta_secs: number | null;
+};
+
+export type ScanLimitsHit = {
+  /** Folders at the depth limit have totals but no children */
+  max_depth: boolean;
+  max_entries: boolean;
+  timeout: boolean;
+};
+
+/** Fields of every result computed from the stored scan tree */
+export type FromScan = {
+  /** Limits the scan stopped at; entries below them are missing from the result */
+  limits_hit: ScanLimitsHit;
 };
 
 type ScanCompleteEvent = {
   root: DirNode;
   total_scanned: number;
+  limits_hit: ScanLimitsHit;
+};
+
+type ScanSubtree = {
+  parent_path: string;
+  node: DirNode;
+};
+
+/** Subtrees finished since the last progress event */
+type ScanSubtreesEvent = {
+  subtrees: ScanSubtree[];
+  total_scanned: number;
+};
+
+type ScanCancelledEvent = {
+  /** Partial tree, absent if nothing had been read yet */
+  root: DirNode | null;
+  total_scanned: number;
 };
 
 type ScanErrorEvent = {
   message: string;
 };
 
+type ScanUpdatedEvent = {
+  upserted: DirNode[];
+  removed: string[];
+  /** Whole new tree after a full rescan, sent when filesystem events were dropped */
+  root?: DirNode;
+  sizes: {
+    path: string;
+    size: number;
+    item_count: number;
+    stale_size: number;
+    mtime?: number;
+  }[];
+};
+
+/** Optional scanner tuning; omitted fields use backend defaults */
+/** Merges each folder's smallest children into one summary node */
+export interface Aggregation {
+  /** Children smaller than this many bytes are merged */
+  min_size?: number;
+  /** Children beyond the largest top_k are merged */
+  top_k?: number;
+}
+
+/** One page of a folder's children from load_children */
+export interface ChildrenPage {
+  /** Children before paging, counting a summary node as one */
+  total: number;
+  offset: number;
+  items: DirNode[];
+}
+
+export interface ScanConfig {
+  /** Worker threads, 80% of cores by default */
+  threads?: number;
+  /** Maximum stat/read_dir operations per second */
+  max_ops_per_sec?: number;
+  /** Lowest CPU and idle I/O priority (Linux) */
+  low_priority?: boolean;
+  stale_days?: number;
+  /** Folders this many levels down keep their totals but not their children */
+  max_depth?: number;
+  /** Stop after this many files and folders */
+  max_entries?: number;
+  /** Stop after this many seconds */
+  timeout_secs?: number;
+  aggregate?: Aggregation;
+  /** Count links themselves (default), follow them, or leave them out */
+  symlinks?: 'link' | 'follow' | 'skip';
+}
+
+export interface AgeHistogram extends FromScan {
+  path: string;
+  buckets: { label: string; max_age_days: number | null; size: number; count: number }[];
+  unknown_size: number;
+  unknown_count: number;
+  newest_mtime?: number;
+}
+
+export interface OwnerUsage {
+  id: number;
+  name: string | null;
+  size: number;
+  file_count: number;
+}
+
+export interface OwnerBreakdown extends FromScan {
+  path: string;
+  users: OwnerUsage[];
+  groups: OwnerUsage[];
+}
+
+/** Per-user and per-group usage for the scan root, or for a directory within it */
+export function fetchOwnerUsage(path?: string): Promise<OwnerBreakdown> {
+  return invoke<OwnerBreakdown>('owner_usage', { path: path ?? null });
+}
+
+export function fetchAgeHistogram(path: string): Promise<AgeHistogram> {
+  return invoke<AgeHistogram>('age_histogram', { path });
+}
+
 const MAX_HISTORY_ENTRIES = 5;
 
 const initial: ScanState = {
   scanning: false,
+  paused: false,
   data: null,
+  partial: null,
   totalScanned: 0,
   currentPath: '',
+  progress: null,
+  limitsHit: null,
+  config: null,
   error: null,
+  watching: false,
   history: []
 };
 
@@ -77,7 +232,8 @@ function createScanStore() {
     updateIfScanning((s) => ({
       ...s,
       currentPath: event.payload.path,
-      totalScanned: event.payload.total_scanned
+      totalScanned: event.payload.total_scanned,
+      progress: event.payload
     }));
 
   const handleComplete = (event: { payload: ScanCompleteEvent }) =>
@@ -85,6 +241,9 @@ function createScanStore() {
       ...s,
       scanning: false,
       data: event.payload.root,
+      partial: null,
+      progress: null,
+      limitsHit: event.payload.limits_hit,
       totalScanned: event.payload.total_scanned,
       currentPath: '',
       history: addOrUpdateHistory(s.history, {
@@ -94,6 +253,68 @@ function createScanStore() {
       })
     }));
 
+  const pendingDir = (path: string): DirNode => ({
+    name: path.slice(Math.max(path.lastIndexOf('/'), path.lastIndexOf('\\')) + 1),
+    path,
+    size: 0,
+    item_count: 0,
+    children: [],
+    is_file: false,
+    pending: true
+  });
+
+  const insertSubtree = (root: DirNode, parent: string, node: DirNode): DirNode => {
+    let children: DirNode[];
+    if (root.path === parent) {
+      children = root.children.filter((child) => child.path !== node.path).concat(node);
+    } else {
+      // Subtrees stream at most two levels deep, so any other parent is a direct child
+      const holder = root.children.find((child) => child.path === parent) ?? pendingDir(parent);
+      children = root.children
+        .filter((child) => child.path !== parent)
+        .concat(insertSubtree(holder, parent, node));
+    }
+
+    // Finished directories arrive with exact totals; placeholders sum what has arrived so far
+    if (!root.pending) return { ...root, children };
+    return {
+      ...root,
+      children,
+      size: children.reduce((sum, child) => sum + child.size, 0),
+      item_count: children.reduce((sum, child) => sum + child.item_count, 0)
+    };
+  };
+
+  const handleSubtrees = (event: { payload: ScanSubtreesEvent }) =>
+    updateIfScanning((s) => {
+      if (!s.partial) return s;
+      const { subtrees, total_scanned } = event.payload;
+      return {
+        ...s,
+        partial: subtrees.reduce(
+          (partial, { parent_path, node }) => insertSubtree(partial, parent_path, node),
+          s.partial
+        ),
+        totalScanned: Math.max(s.totalScanned, total_scanned)
+      };
+    });
+
+  const handleCancelled = (event: { payload: ScanCancelledEvent }) =>
+    updateIfScanning((s) =>
+      event.payload.root
+        ? {
+            ...s,
+            scanning: false,
+            paused: false,
+            data: event.payload.root,
+            partial: null,
+            progress: null,
+            totalScanned: event.payload.total_scanned,
+            currentPath: ''
+          }
+        : { ...initial, history: s.history }
+    );
+
   const handleError = (event: { payload: ScanErrorEvent }) =>
     updateIfScanning((s) => ({
       ...s,
@@ -102,18 +323,56 @@ function createScanStore() {
       currentPath: ''
     }));
 
+  const parentPath = (path: string) =>
+    path.slice(0, Math.max(path.lastIndexOf('/'), path.lastIndexOf('\\')));
+
+  const applyUpdate = (node: DirNode, update: ScanUpdatedEvent): DirNode => {
+    if (node.is_file) return node;
+
+    const totals = update.sizes.find((entry) => entry.path === node.path);
+    // Only splice into directories whose children are loaded; lazy ones fetch fresh data
+    const added = node.has_children
+      ? []
+      : update.upserted.filter((child) => parentPath(child.path) === node.path);
+    const children = (node.children ?? [])
+      .filter((child) => !update.removed.includes(child.path))
+      .filter((child) => !added.some((a) => a.path === child.path))
+      .concat(added)
+      .map((child) => applyUpdate(child, update));
+
+    return {
+      ...node,
+      size: totals?.size ?? node.size,
+      item_count: totals?.item_count ?? node.item_count,
+      stale_size: totals?.stale_size ?? node.stale_size,
+      mtime: totals?.mtime ?? node.mtime,
+      children
+    };
+  };
+
+  const handleUpdated = (event: { payload: ScanUpdatedEvent }) =>
+    update((s) => {
+      if (!s.data) return s;
+      if (event.payload.root) return { ...s, data: event.payload.root };
+      return { ...s, data: applyUpdate(s.data, event.payload) };
+    });
+
   const setupListeners = async () => {
     await cleanup();
     listeners = [
       await listen('scan:directory_complete', handleProgress),
+      await listen('scan:subtrees', handleSubtrees),
       await listen('scan:complete', handleComplete),
-      await listen('scan:error', handleError)
+      await listen('scan:cancelled', handleCancelled),
+      await listen('scan:error', handleError),
+      await listen('scan:updated', handleUpdated)
     ];
   };
 
   const removeNode = (root: DirNode | null, pathToRemove: string): DirNode | null => {
     if (!root) return null;
-    if (root.path === pathToRemove) return null;
+    // Summaries merge several entries and are never removed on their own
+    if (root.path === pathToRemove) return root.kind === 'summary' ? root : null;
 
     if (!root.is_file && root.children) {
       const updatedChildren = root.children
@@ -131,33 +390,66 @@ function createScanStore() {
     return root;
   };
 
-  const startScan = async (path: string) => {
-    update((s) => ({ ...initial, history: s.history, scanning: true, currentPath: path }));
+  const startScan = async (path: string, config?: ScanConfig) => {
+    update((s) => ({
+      ...initial,
+      history: s.history,
+      scanning: true,
+      currentPath: path,
+      partial: pendingDir(path),
+      config: config ?? null
+    }));
     await setupListeners();
     try {
-      await invoke('scan_directory', { path });
+      await invoke('scan_directory', { path, config: config ?? null });
     } catch (err) {
       update((s) => ({ ...s, scanning: false, error: String(err) }));
     }
   };
 
+  // The partial tree (or nothing) arrives through scan:cancelled
   const cancelScan = async () => {
     try {
       await invoke('cancel_scan');
     } catch (err) {
       console.error('Failed to cancel scan:', err);
+      update((s) => ({ ...initial, history: s.history }));
+    }
+  };
+
+  const togglePause = async () => {
+    const { paused } = get({ subscribe });
+    try {
+      await invoke(paused ? 'resume_scan' : 'pause_scan');
+      updateIfScanning((s) => ({ ...s, paused: !paused }));
+    } catch (err) {
+      console.error('Failed to toggle pause:', err);
     }
-    update((s) => ({ ...initial, history: s.history }));
   };
 
   return {
     subscribe,
     startScan,
     cancelScan,
+    togglePause,
+    async toggleWatch() {
+      const { watching } = get({ subscribe });
+      try {
+        await invoke(watching ? 'unwatch_scan' : 'watch_scan');
+        update((s) => ({ ...s, watching: !watching }));
+      } catch (err) {
+        console.error('Failed to toggle watching:', err);
+      }
+    },
     removeNode(path: string) {
       update((s) => ({ ...s, data: removeNode(s.data, path) }));
     },
-    reset: () => update((s) => ({ ...initial, history: s.history })),
+    reset: () => {
+      if (get({ subscribe }).watching) {
+        invoke('unwatch_scan').catch((err) => console.error('Failed to stop watching:', err));
+      }
+      update((s) => ({ ...initial, history: s.history }));
+    },
     openHistory(path: string) {
       update((s) => {
         if (s.scanning) return s;
@@ -167,6 +459,7 @@ function createScanStore() {
         return {
           ...s,
           data: match.root,
+          limitsHit: null,
           error: null,
           currentPath: '',
           totalScanned: match.root.size ?? s.totalScanned
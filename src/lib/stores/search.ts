@@ -0,0 +1,80 @@
+import { writable } from 'svelte/store';
+import { invoke } from '@tauri-apps/api/core';
+import type { DirNode, FromScan } from './scan';
+
+export type SortKey = 'size' | 'name' | 'path' | 'modified' | 'item_count';
+
+export interface SearchOptions {
+  root?: string;
+  sort?: SortKey;
+  descending?: boolean;
+  offset?: number;
+  limit?: number;
+}
+
+export type SearchHit = Pick<DirNode, 'name' | 'path' | 'size' | 'item_count' | 'is_file'>;
+
+export interface SearchResults extends FromScan {
+  total: number;
+  offset: number;
+  items: SearchHit[];
+}
+
+interface NameSearchResults extends FromScan {
+  total: number;
+  items: SearchHit[];
+}
+
+interface SearchState {
+  query: string;
+  options: SearchOptions;
+  results: SearchResults | null;
+  loading: boolean;
+  error: string | null;
+}
+
+export const PAGE_SIZE = 100;
+
+function createSearchStore() {
+  const initial: SearchState = {
+    query: '',
+    options: { sort: 'size', offset: 0, limit: PAGE_SIZE },
+    results: null,
+    loading: false,
+    error: null
+  };
+  const { subscribe, set, update } = writable<SearchState>(initial);
+
+  return {
+    subscribe,
+    /** Run a query against the last scan, e.g. `size>1GB ext:iso age>2y` */
+    async run(query: string, options: SearchOptions = {}) {
+      const merged = { ...initial.options, ...options };
+      update((state) => ({ ...state, query, options: merged, loading: true, error: null }));
+      try {
+        const results = await invoke<SearchResults>('search', { query, options: merged });
+        update((state) => ({ ...state, results, loading: false }));
+      } catch (err) {
+        update((state) => ({ ...state, results: null, loading: false, error: String(err) }));
+      }
+    },
+    /** Substring or fuzzy name lookup through the backend name index */
+    async runNames(query: string, fuzzy = false) {
+      update((state) => ({ ...state, query, loading: true, error: null }));
+      try {
+        const found = await invoke<NameSearchResults>('search_names', {
+          query,
+          fuzzy,
+          limit: PAGE_SIZE
+        });
+        const results = { total: found.total, offset: 0, items: found.items };
+        update((state) => ({ ...state, results, loading: false }));
+      } catch (err) {
+        update((state) => ({ ...state, results: null, loading: false, error: String(err) }));
+      }
+    },
+    clear: () => set(initial)
+  };
+}
+
+export const searchStore = createSearchStore();
@@ -1,5 +1,8 @@
 import { writable } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export type VolumeKind = 'physical' | 'network' | 'virtual' | 'overlay' | 'loop';
 
 export interface VolumeInfo {
   name: string;
@@ -8,37 +11,126 @@ export interface VolumeInfo {
   availableSpace: number;
   fileSystem: string;
   isRemovable: boolean;
+  usedPercent: number;
+  totalInodes: number | null;
+  freeInodes: number | null;
+  reservedSpace: number | null;
+  device: string;
+  mountOptions: string[];
+  kind: VolumeKind;
+  isBindMount: boolean;
+}
+
+export interface AlertThreshold {
+  mountPoint: string;
+  minFreePercent?: number | null;
+  minFreeBytes?: number | null;
 }
 
 interface VolumeState {
   volumes: VolumeInfo[];
   loading: boolean;
   error: string | null;
+  thresholds: AlertThreshold[];
+  /** Mount points currently below their alert threshold */
+  activeAlerts: string[];
 }
 
 const initialState: VolumeState = {
   volumes: [],
   loading: false,
-  error: null
+  error: null,
+  thresholds: [],
+  activeAlerts: []
+};
+
+type VolumesChangedEvent = {
+  volumes: VolumeInfo[];
+};
+
+type VolumeAlertEvent = {
+  mountPoint: string;
+  state: 'triggered' | 'resolved';
+};
+
+type VolumeAlertsStatus = {
+  thresholds: AlertThreshold[];
+  active: string[];
+};
+
+const THRESHOLDS_STORAGE_KEY = 'diskly:volume-alerts';
+
+const loadSavedThresholds = (): AlertThreshold[] => {
+  try {
+    return JSON.parse(localStorage.getItem(THRESHOLDS_STORAGE_KEY) ?? '[]');
+  } catch {
+    return [];
+  }
 };
 
 function createVolumeStore() {
-  const { subscribe, set, update } = writable<VolumeState>(initialState);
+  const { subscribe, update } = writable<VolumeState>(initialState);
+  let unlistenChanges: UnlistenFn[] = [];
+  let hasThresholds = false;
+
+  const handleAlert = (event: { payload: VolumeAlertEvent }) =>
+    update((state) => {
+      const others = state.activeAlerts.filter((mount) => mount !== event.payload.mountPoint);
+      return {
+        ...state,
+        activeAlerts:
+          event.payload.state === 'triggered' ? [...others, event.payload.mountPoint] : others
+      };
+    });
+
+  const applyThresholds = async (thresholds: AlertThreshold[]) => {
+    await invoke('set_volume_alerts', { thresholds });
+    localStorage.setItem(THRESHOLDS_STORAGE_KEY, JSON.stringify(thresholds));
+    hasThresholds = thresholds.length > 0;
+    const status = await invoke<VolumeAlertsStatus>('get_volume_alerts');
+    update((state) => ({ ...state, thresholds, activeAlerts: status.active }));
+  };
 
   return {
     subscribe,
-    async refresh() {
+    /** Pass includeAll to also list virtual, overlay, loop and bind mounts */
+    async refresh(includeAll = false) {
       update((state) => ({ ...state, loading: true, error: null }));
       try {
-        const volumes = await invoke<VolumeInfo[]>('list_volumes');
-        set({ volumes, loading: false, error: null });
+        const volumes = await invoke<VolumeInfo[]>('list_volumes', { includeAll });
+        update((state) => ({ ...state, volumes, loading: false, error: null }));
       } catch (err) {
-        set({
+        update((state) => ({
+          ...state,
           volumes: [],
           loading: false,
           error: err instanceof Error ? err.message : String(err)
-        });
+        }));
+      }
+    },
+    /** Keep volumes up to date from backend polling until stopMonitor is called */
+    async startMonitor(includeAll = false) {
+      unlistenChanges.splice(0).forEach((fn) => fn());
+      unlistenChanges = [
+        await listen<VolumesChangedEvent>('volumes:changed', (event) =>
+          update((state) => ({ ...state, volumes: event.payload.volumes }))
+        ),
+        await listen<VolumeAlertEvent>('volumes:alert', handleAlert)
+      ];
+      await invoke('start_volume_monitor', { includeAll });
+      await applyThresholds(loadSavedThresholds());
+    },
+    /** Stops live updates; the backend keeps polling while alert thresholds are set */
+    async stopMonitor() {
+      unlistenChanges.splice(0).forEach((fn) => fn());
+      if (!hasThresholds) {
+        await invoke('stop_volume_monitor');
       }
+    },
+    /** Set or clear (with null) the low-space alert for a mount point */
+    async setThreshold(mountPoint: string, threshold: Omit<AlertThreshold, 'mountPoint'> | null) {
+      const others = loadSavedThresholds().filter((t) => t.mountPoint !== mountPoint);
+      await applyThresholds(threshold ? [...others, { mountPoint, ...threshold }] : others);
     }
   };
 }